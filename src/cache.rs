@@ -1,5 +1,22 @@
-use crate::structs::{Cache, StoredGameData};
-use std::{fs, path::Path};
+use crate::structs::{Cache, SavedServer, StagedLaunch, StoredGameData};
+use std::{
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Recently joined servers that are kept besides favorites
+const MAX_RECENT_SERVERS: usize = 20;
+
+/// Seconds a staged launch stays valid, Steam may take a while to start the game
+const STAGED_LAUNCH_TIMEOUT: u64 = 300;
+
+pub fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
 
 pub fn get_cache(dir: &Path) -> Cache {
     let cache_path = dir.join("awcache.json");
@@ -25,6 +42,15 @@ pub fn get_stored_data() -> Option<StoredGameData> {
     cache.stored_data
 }
 
+/// Engine of `client` according to the stored game data
+pub fn engine_of(client: &str) -> Option<String> {
+    get_stored_data()?
+        .clients
+        .into_iter()
+        .find(|(_, clients)| clients.iter().any(|c| c == client))
+        .map(|(engine, _)| engine)
+}
+
 pub fn store_game_data(data: &StoredGameData) -> Result<(), Box<dyn std::error::Error>> {
     let dir = std::env::current_dir()?;
     let mut cache = get_cache(&dir);
@@ -32,3 +58,82 @@ pub fn store_game_data(data: &StoredGameData) -> Result<(), Box<dyn std::error::
     save_cache(&dir, cache);
     Ok(())
 }
+
+/// Favorites first, then recently joined servers, most recent first
+pub fn get_saved_servers(dir: &Path) -> Vec<SavedServer> {
+    let mut servers = get_cache(dir).servers;
+    servers.sort_by(|a, b| {
+        b.favorite
+            .cmp(&a.favorite)
+            .then(b.last_played.cmp(&a.last_played))
+    });
+    servers
+}
+
+/// Remember a joined server, keeping the stored hostname if none is known
+pub fn record_server(dir: &Path, address: &str, hostname: Option<&str>) {
+    let mut cache = get_cache(dir);
+    let index = match cache.servers.iter().position(|s| s.address == address) {
+        Some(index) => index,
+        None => {
+            cache.servers.push(SavedServer {
+                address: address.to_string(),
+                ..Default::default()
+            });
+            cache.servers.len() - 1
+        }
+    };
+    let server = &mut cache.servers[index];
+    server.last_played = unix_time();
+    if let Some(hostname) = hostname.filter(|h| !h.is_empty()) {
+        server.hostname = hostname.to_string();
+    }
+
+    // drop the oldest non-favorites
+    let (favorites, mut recent): (Vec<_>, Vec<_>) =
+        cache.servers.into_iter().partition(|s| s.favorite);
+    recent.sort_by_key(|s| std::cmp::Reverse(s.last_played));
+    recent.truncate(MAX_RECENT_SERVERS);
+    cache.servers = favorites.into_iter().chain(recent).collect();
+
+    save_cache(dir, cache);
+}
+
+/// Add a server to the favorites or update its tags, unfavoriting keeps it in the recent list
+pub fn set_favorite(dir: &Path, address: &str, favorite: bool, tags: &[String]) {
+    let mut cache = get_cache(dir);
+    match cache.servers.iter_mut().find(|s| s.address == address) {
+        Some(server) => {
+            server.favorite = favorite;
+            if favorite {
+                server.tags = tags.to_vec();
+            }
+        }
+        None if favorite => cache.servers.push(SavedServer {
+            address: address.to_string(),
+            favorite,
+            tags: tags.to_vec(),
+            ..Default::default()
+        }),
+        None => return,
+    }
+    save_cache(dir, cache);
+}
+
+pub fn stage_launch(dir: &Path, client: &str, args: &[String]) {
+    let mut cache = get_cache(dir);
+    cache.staged_launch = Some(StagedLaunch {
+        client: client.to_string(),
+        args: args.to_vec(),
+        timestamp: unix_time(),
+    });
+    save_cache(dir, cache);
+}
+
+/// Remove the staged launch from the cache, returning it if it hasn't expired
+pub fn take_staged_launch(dir: &Path) -> Option<StagedLaunch> {
+    let mut cache = get_cache(dir);
+    let staged = cache.staged_launch.take()?;
+    save_cache(dir, cache);
+    (unix_time().saturating_sub(staged.timestamp) <= STAGED_LAUNCH_TIMEOUT).then_some(staged)
+}
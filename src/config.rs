@@ -45,6 +45,9 @@ pub fn save_value(config_path: PathBuf, key: &str, value: bool) {
         "use_https" => config.use_https = value,
         "skip_redist" => config.skip_redist = value,
         "prerelease" => config.prerelease = value,
+        "manage_prefix" => config.manage_prefix = value,
+        "steam_launch" => config.steam_launch = value,
+        "supervise" => config.supervise = value,
         _ => (),
     }
     save(config_path, config);
@@ -55,6 +58,11 @@ pub fn save_value_s(config_path: PathBuf, key: &str, value: String) {
     match key {
         "args" => config.args = value.to_string(),
         "engine" => config.engine = value.to_string(),
+        "runner" => config.runner = value.to_string(),
+        "runner_command" => config.runner_command = value.to_string(),
+        "launch_command" => config.launch_command = value.to_string(),
+        "wine_prefix" => config.wine_prefix = value.to_string(),
+        "proton_version" => config.proton_version = value.to_string(),
         _ => (),
     }
     save(config_path, config);
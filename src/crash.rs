@@ -0,0 +1,353 @@
+use crate::extend::*;
+use crate::global::MASTER_URL;
+use crate::misc::{self, is_password_dvar, redact_args, REDACTED};
+use crate::structs::{CdnFile, Config};
+use crate::{cache, http_async, logs};
+use serde_json::{json, Value};
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+/// Crash dumps included in a report, the newest first
+const MAX_DUMPS: usize = 5;
+
+/// Larger crash dumps are left out, full memory dumps can be gigabytes
+const MAX_DUMP_SIZE: u64 = 256 * 1024 * 1024;
+
+/// Time files.json gets to arrive, a crash report shouldn't wait on a dead connection
+const MANIFEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Secrets shorter than this are only removed where they are set, e.g. after a password dvar.
+/// Replacing every occurrence of a short value would mangle the rest of the report.
+const MIN_SECRET_LEN: usize = 6;
+
+/// Words in a dvar or variable name that mark its value as a secret
+const SECRET_WORDS: [&str; 4] = ["PASS", "TOKEN", "SECRET", "KEY"];
+
+fn is_secret_key(key: &str) -> bool {
+    let upper = key.to_ascii_uppercase();
+    SECRET_WORDS.iter().any(|word| upper.contains(word))
+}
+
+/// Passwords, tokens and webhook URLs from the config that must not show up anywhere in a report,
+/// the longest first
+fn secrets(cfg: &Config) -> Vec<String> {
+    let mut secrets = vec![crate::rcon::password(cfg)];
+    secrets.extend(
+        cfg.env
+            .iter()
+            .filter(|(key, _)| is_secret_key(key))
+            .map(|(_, value)| value.clone()),
+    );
+    secrets.extend(cfg.webhooks.iter().map(|webhook| webhook.url.clone()));
+    for args in std::iter::once(&cfg.args).chain(cfg.instances.iter().map(|i| &i.args)) {
+        let words = misc::split_args(args);
+        for pair in words.windows(2) {
+            if is_password_dvar(&pair[0]) {
+                secrets.push(pair[1].clone());
+            }
+        }
+    }
+    secrets.retain(|secret| secret.len() >= MIN_SECRET_LEN);
+    secrets.sort_by_key(|secret| std::cmp::Reverse(secret.len()));
+    secrets
+}
+
+fn redact_line(line: &str) -> String {
+    let mut previous = "";
+    // quote that opened a redacted value spanning several words
+    let mut open_quote: Option<char> = None;
+    let mut words = Vec::new();
+    for word in line.split(' ') {
+        if let Some(quote) = open_quote {
+            if word.ends_with(quote) {
+                open_quote = None;
+            }
+        } else if !word.is_empty() && is_password_dvar(previous) {
+            open_quote = word
+                .chars()
+                .next()
+                .filter(|c| (*c == '"' || *c == '\'') && !(word.len() > 1 && word.ends_with(*c)));
+            words.push(REDACTED.to_string());
+        } else {
+            words.push(match word.split_once('=') {
+                Some((key, _)) if is_secret_key(key) => format!("{key}={REDACTED}"),
+                _ => word.to_string(),
+            });
+        }
+        previous = word;
+    }
+    words.join(" ")
+}
+
+/// Remove secrets from a log: values known from the config, values following password dvars
+/// and secret looking `KEY=value` pairs like the launch environment
+pub fn redact_log(text: &str, cfg: &Config) -> String {
+    let mut text = text.to_string();
+    for secret in secrets(cfg) {
+        text = text.replace(&secret, REDACTED);
+    }
+    let mut redacted: String = text.lines().map(redact_line).collect::<Vec<_>>().join("\n");
+    if text.ends_with('\n') {
+        redacted.push('\n');
+    }
+    redacted
+}
+
+/// The config as JSON with passwords, webhook URLs, hook commands and secret looking environment variables removed
+pub fn redacted_config(cfg: &Config) -> Value {
+    let mut config = serde_json::to_value(cfg).unwrap_or_default();
+    if !cfg.rcon_password.is_empty() {
+        config["rcon_password"] = json!(REDACTED);
+    }
+    config["args"] = json!(redact_args(&cfg.args));
+    for (i, instance) in cfg.instances.iter().enumerate() {
+        config["instances"][i]["args"] = json!(redact_args(&instance.args));
+    }
+    // webhook URLs carry the token that allows posting
+    for i in 0..cfg.webhooks.len() {
+        config["webhooks"][i]["url"] = json!(REDACTED);
+    }
+    // hook commands can hold credentials, e.g. a curl call with a token
+    for hook in ["pre_update", "post_update", "pre_launch", "post_exit"] {
+        if config["hooks"][hook]
+            .as_str()
+            .is_some_and(|command| !command.is_empty())
+        {
+            config["hooks"][hook] = json!(REDACTED);
+        }
+    }
+    for key in cfg.env.keys() {
+        if is_secret_key(key) {
+            config["env"][key] = json!(REDACTED);
+        }
+    }
+    config
+}
+
+/// What awcache.json knows about the install, without the saved server addresses
+pub fn cache_summary(dir: &Path) -> Value {
+    let cache = cache::get_cache(dir);
+    json!({
+        "iw4x_revision": cache.iw4x_revision,
+        "hashes": cache.hashes.len(),
+        "stored_data": cache.stored_data,
+        "saved_servers": cache.servers.len(),
+        "staged_launch": cache.staged_launch.is_some(),
+    })
+}
+
+/// Compare the files of `engine` in the install with the manifest, one line per file
+pub fn hash_report(dir: &Path, engine: &str, manifest: &[CdnFile]) -> String {
+    let remote_dir_pre = format!("{engine}/");
+    let mut report = String::new();
+    for file in manifest {
+        let Some(name) = file.name.strip_prefix(&remote_dir_pre) else {
+            continue;
+        };
+        let path = dir.join(name);
+        let status = if !path.is_file() {
+            "missing"
+        } else {
+            match path.get_blake3() {
+                Ok(hash) if hash.eq_ignore_ascii_case(&file.blake3) => "ok",
+                Ok(_) => "modified",
+                Err(_) => "unreadable",
+            }
+        };
+        report.push_str(&format!("{status:<10} {name}\n"));
+    }
+    report
+}
+
+fn fetch_manifest() -> Result<Vec<CdnFile>, String> {
+    let url = format!("{}/files.json", MASTER_URL.lock().unwrap());
+    // launch runs on the main runtime without being async, so the request gets its own
+    std::thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| e.to_string())?;
+        runtime.block_on(async {
+            let body = tokio::time::timeout(MANIFEST_TIMEOUT, http_async::get_body_string(&url))
+                .await
+                .map_err(|_| "Timed out fetching files.json".to_string())??;
+            serde_json::from_str(&body).map_err(|e| format!("Failed to parse files.json: {e}"))
+        })
+    })
+    .join()
+    .unwrap_or_else(|_| Err("Failed to fetch files.json".to_string()))
+}
+
+/// Crash dumps in the install written since `since`, the newest first
+pub fn crash_dumps(dir: &Path, since: SystemTime) -> Vec<PathBuf> {
+    let mut dumps: Vec<(SystemTime, PathBuf)> = walkdir::WalkDir::new(dir)
+        .max_depth(3)
+        .into_iter()
+        .flatten()
+        .filter(|entry| {
+            entry.file_type().is_file()
+                && entry.path().extension().is_some_and(|extension| {
+                    extension.eq_ignore_ascii_case("dmp") || extension.eq_ignore_ascii_case("mdmp")
+                })
+        })
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            let modified = metadata.modified().ok()?;
+            (modified >= since && metadata.len() <= MAX_DUMP_SIZE)
+                .then(|| (modified, entry.into_path()))
+        })
+        .collect();
+    dumps.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    dumps
+        .into_iter()
+        .take(MAX_DUMPS)
+        .map(|(_, path)| path)
+        .collect()
+}
+
+#[cfg(unix)]
+fn runner_info(cfg: &Config) -> String {
+    match crate::runner::Runner::from_config(cfg) {
+        Ok(runner) => format!(
+            "{} ({})",
+            runner.name(),
+            runner.version().as_deref().unwrap_or("unknown version")
+        ),
+        Err(error) => error,
+    }
+}
+
+#[cfg(windows)]
+fn runner_info(_cfg: &Config) -> String {
+    "native".to_string()
+}
+
+/// Add a log with its secrets removed
+fn add_log(zip: &mut ZipWriter<File>, name: &str, path: &Path, cfg: &Config) -> Result<(), String> {
+    let log = fs::read(path).map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    add_text(zip, name, &redact_log(&String::from_utf8_lossy(&log), cfg))
+}
+
+fn add_file(zip: &mut ZipWriter<File>, name: &str, path: &Path) -> Result<(), String> {
+    let mut file =
+        File::open(path).map_err(|e| format!("Failed to open {}: {e}", path.display()))?;
+    zip.start_file(name, options()).map_err(|e| e.to_string())?;
+    io::copy(&mut file, zip).map_err(|e| format!("Failed to add {}: {e}", path.display()))?;
+    Ok(())
+}
+
+fn add_text(zip: &mut ZipWriter<File>, name: &str, text: &str) -> Result<(), String> {
+    zip.start_file(name, options()).map_err(|e| e.to_string())?;
+    zip.write_all(text.as_bytes()).map_err(|e| e.to_string())
+}
+
+fn options() -> SimpleFileOptions {
+    SimpleFileOptions::default().compression_method(CompressionMethod::Deflated)
+}
+
+/// Details of a game that exited with an error
+pub struct Crash<'a> {
+    pub client: &'a str,
+    pub install_path: &'a Path,
+    pub exit_status: String,
+    /// Launch time of the game, crash dumps older than this are left out
+    pub started: SystemTime,
+    pub game_log: Option<&'a Path>,
+    pub launcher_log: Option<&'a Path>,
+}
+
+/// Write everything needed to look into a crash to a zip archive in `dir`, returning its path
+pub fn write_report(
+    dir: &Path,
+    crash: &Crash,
+    cfg: &Config,
+    manifest: Result<Vec<CdnFile>, String>,
+) -> Result<PathBuf, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+    let path = dir.join(format!("crash-{}-{}.zip", crash.client, cache::unix_time()));
+    let file =
+        File::create(&path).map_err(|e| format!("Failed to create {}: {e}", path.display()))?;
+    let mut zip = ZipWriter::new(file);
+
+    let engine = cache::engine_of(crash.client);
+    let summary = format!(
+        "AlterWare Launcher {} on {}/{}\n\
+         Client: {}\n\
+         Engine: {}\n\
+         Install: {}\n\
+         Exit: {}\n\
+         Time: {}\n\
+         Runner: {}\n",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        crash.client,
+        engine.as_deref().unwrap_or("unknown"),
+        crash.install_path.display(),
+        crash.exit_status,
+        cache::unix_time(),
+        runner_info(cfg),
+    );
+    add_text(&mut zip, "summary.txt", &summary)?;
+
+    // files that don't exist are skipped, the report is still useful without them.
+    // the launcher log has the launch command and environment, so logs are redacted like the config
+    if let Some(game_log) = crash.game_log {
+        let rotated = PathBuf::from(format!("{}.1", game_log.display()));
+        if rotated.is_file() {
+            add_log(&mut zip, "game.log.1", &rotated, cfg)?;
+        }
+        if game_log.is_file() {
+            add_log(&mut zip, "game.log", game_log, cfg)?;
+        }
+    }
+    if let Some(launcher_log) = crash.launcher_log.filter(|log| log.is_file()) {
+        add_log(&mut zip, "launcher.log", launcher_log, cfg)?;
+    }
+
+    let config = serde_json::to_string_pretty(&redacted_config(cfg)).unwrap_or_default();
+    add_text(&mut zip, "alterware-launcher.json", &config)?;
+    let cache =
+        serde_json::to_string_pretty(&cache_summary(crash.install_path)).unwrap_or_default();
+    add_text(&mut zip, "awcache.json", &cache)?;
+
+    let hashes = match (engine, manifest) {
+        (Some(engine), Ok(manifest)) => hash_report(crash.install_path, &engine, &manifest),
+        (None, _) => "Engine of the client is unknown, no files were compared\n".to_string(),
+        (_, Err(error)) => format!("No files were compared: {error}\n"),
+    };
+    add_text(&mut zip, "hashes.txt", &hashes)?;
+
+    for dump in crash_dumps(crash.install_path, crash.started) {
+        let name = dump
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        add_file(&mut zip, &format!("dumps/{name}"), &dump)?;
+    }
+
+    zip.finish()
+        .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+    Ok(path)
+}
+
+/// Collect a crash report next to the game logs, or in the install if there is no log directory
+pub fn collect(crash: &Crash, cfg: &Config) -> Result<PathBuf, String> {
+    let dir = logs::game_log_dir().unwrap_or_else(|| crash.install_path.to_path_buf());
+    let manifest = if cfg.offline {
+        Err("offline mode".to_string())
+    } else {
+        fetch_manifest()
+    };
+    info!(
+        "Writing crash report for {} to {}",
+        crash.client,
+        dir.display()
+    );
+    write_report(&dir, crash, cfg, manifest)
+}
@@ -0,0 +1,224 @@
+use crate::misc;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Key identifying .desktop files created by the launcher
+const CLIENT_KEY: &str = "X-AlterWare-Client";
+
+/// Entry handling `alterware://` links, not shown in the application menu
+const HANDLER_FILE: &str = "alterware-connect.desktop";
+const HANDLER_MIME: &str = "x-scheme-handler/alterware";
+
+/// Freedesktop icons have to be PNG, SVG or XPM, this is the largest image of icon.ico.
+/// Every client uses the launcher icon.
+const ICON: &[u8] = include_bytes!("../res/icon.png");
+const ICON_FILE: &str = "alterware.png";
+/// Icon written by older versions
+const LEGACY_ICON_FILE: &str = "alterware.ico";
+
+/// `$XDG_DATA_HOME/applications`, where application menu entries live
+pub fn applications_dir() -> Option<PathBuf> {
+    misc::data_home().map(|dir| dir.join("applications"))
+}
+
+/// The user's desktop directory as reported by xdg-user-dir, `~/Desktop` otherwise
+pub fn desktop_dir() -> Option<PathBuf> {
+    if misc::is_program_in_path("xdg-user-dir") {
+        if let Ok(output) = std::process::Command::new("xdg-user-dir")
+            .arg("DESKTOP")
+            .output()
+        {
+            let dir = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            if output.status.success() && !dir.is_empty() && Path::new(&dir).is_dir() {
+                return Some(PathBuf::from(dir));
+            }
+        }
+    }
+
+    std::env::var_os("HOME")
+        .map(|home| Path::new(&home).join("Desktop"))
+        .filter(|dir| dir.is_dir())
+}
+
+/// Quote an argument for the `Exec` key according to the desktop entry spec
+pub fn exec_quote(arg: &str) -> String {
+    let arg = arg.replace('%', "%%");
+    let needs_quotes = arg.is_empty()
+        || arg
+            .chars()
+            .any(|c| c.is_whitespace() || "\"'\\><~|&;$*?#()`".contains(c));
+    if !needs_quotes {
+        return arg;
+    }
+
+    let mut quoted = String::from('"');
+    for c in arg.chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+
+    // the string escape rule is applied on top of the quoting rule
+    quoted.replace('\\', "\\\\")
+}
+
+/// File name of the entry for a client
+pub fn file_name(client: &str) -> String {
+    format!("alterware-{client}.desktop")
+}
+
+/// Content of the .desktop file launching `client` from `install_path`
+pub fn render(client: &str, launcher: &Path, install_path: &Path, icon: &Path) -> String {
+    let exec = [
+        exec_quote(&launcher.to_string_lossy()),
+        exec_quote(client),
+        "--path".to_string(),
+        exec_quote(&install_path.to_string_lossy()),
+    ]
+    .join(" ");
+
+    format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name=AlterWare {client}\n\
+         Comment=Update and launch {client} with the AlterWare launcher\n\
+         Exec={exec}\n\
+         Path={}\n\
+         Icon={}\n\
+         Terminal=true\n\
+         Categories=Game;ActionGame;\n\
+         {CLIENT_KEY}={client}\n",
+        install_path.display(),
+        icon.display()
+    )
+}
+
+/// Content of the .desktop file opening `alterware://` links.
+///
+/// The link is passed as the only argument, the install is resolved from the
+/// stored game data in the working directory.
+pub fn render_handler(launcher: &Path, install_path: &Path, icon: &Path) -> String {
+    format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name=AlterWare Launcher\n\
+         Comment=Join servers from alterware:// links\n\
+         Exec={} %u\n\
+         Path={}\n\
+         Icon={}\n\
+         Terminal=true\n\
+         NoDisplay=true\n\
+         MimeType={HANDLER_MIME};\n",
+        exec_quote(&launcher.to_string_lossy()),
+        install_path.display(),
+        icon.display()
+    )
+}
+
+/// Write the bundled launcher icon to the data directory
+pub fn install_icon() -> Result<PathBuf, String> {
+    let dir = misc::data_dir()
+        .ok_or("Unable to determine data directory")?
+        .join("icons");
+    let path = dir.join(ICON_FILE);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+    fs::write(&path, ICON).map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+    let _ = fs::remove_file(dir.join(LEGACY_ICON_FILE));
+    Ok(path)
+}
+
+fn write_entry(dir: &Path, name: &str, content: &str) -> Result<PathBuf, String> {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+    let path = dir.join(name);
+    fs::write(&path, content).map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+    // desktop environments only trust executable entries on the desktop
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755))
+        .map_err(|e| format!("Failed to set permissions for {}: {e}", path.display()))?;
+    Ok(path)
+}
+
+fn update_desktop_database(dir: &Path) {
+    if misc::is_program_in_path("update-desktop-database") {
+        let _ = std::process::Command::new("update-desktop-database")
+            .arg(dir)
+            .status();
+    }
+}
+
+/// Create application menu entries for every client, and optionally desktop shortcuts
+pub fn create_entries(
+    clients: &[&str],
+    launcher: &Path,
+    install_path: &Path,
+    on_desktop: bool,
+) -> Result<Vec<PathBuf>, String> {
+    let applications = applications_dir().ok_or("Unable to determine applications directory")?;
+    let icon = install_icon()?;
+
+    let mut created = Vec::new();
+    for client in clients {
+        let content = render(client, launcher, install_path, &icon);
+        created.push(write_entry(&applications, &file_name(client), &content)?);
+
+        if on_desktop {
+            match desktop_dir() {
+                Some(desktop) => created.push(write_entry(&desktop, &file_name(client), &content)?),
+                None => warn!("No desktop directory found, skipping desktop shortcut for {client}"),
+            }
+        }
+    }
+
+    let handler = render_handler(launcher, install_path, &icon);
+    created.push(write_entry(&applications, HANDLER_FILE, &handler)?);
+
+    update_desktop_database(&applications);
+    if misc::is_program_in_path("xdg-mime") {
+        let _ = std::process::Command::new("xdg-mime")
+            .args(["default", HANDLER_FILE, HANDLER_MIME])
+            .status();
+    }
+    Ok(created)
+}
+
+/// Remove all entries created by the launcher along with the icon
+pub fn remove_entries() -> Vec<PathBuf> {
+    let mut removed = Vec::new();
+
+    for dir in [applications_dir(), desktop_dir()].into_iter().flatten() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "desktop") {
+                continue;
+            }
+            let is_ours = path.file_name().is_some_and(|name| name == HANDLER_FILE)
+                || fs::read_to_string(&path)
+                    .is_ok_and(|c| c.lines().any(|l| l.starts_with(&format!("{CLIENT_KEY}="))));
+            if is_ours && fs::remove_file(&path).is_ok() {
+                removed.push(path);
+            }
+        }
+    }
+
+    if let Some(applications) = applications_dir() {
+        update_desktop_database(&applications);
+    }
+
+    if let Some(icons) = misc::data_dir().map(|dir| dir.join("icons")) {
+        for icon in [ICON_FILE, LEGACY_ICON_FILE].map(|name| icons.join(name)) {
+            if fs::remove_file(&icon).is_ok() {
+                removed.push(icon);
+            }
+        }
+    }
+
+    removed
+}
@@ -0,0 +1,191 @@
+use crate::servers;
+use crate::structs::{InstanceState, InstanceStatus, ServerInstance};
+use crate::supervisor::InstanceFiles;
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// Directory with the state and stop request files of all instances
+pub fn state_dir(install_path: &Path) -> PathBuf {
+    install_path.join("fleet")
+}
+
+/// Install path of an instance, the launcher's install path unless the instance sets its own
+pub fn instance_path(instance: &ServerInstance, install_path: &Path) -> PathBuf {
+    if instance.path.is_empty() {
+        install_path.to_path_buf()
+    } else {
+        PathBuf::from(&instance.path)
+    }
+}
+
+/// `-dedicated +set net_port <port> +exec <server_cfg>` followed by the instance's own args
+pub fn instance_args(instance: &ServerInstance) -> Vec<String> {
+    let mut args = vec![
+        "-dedicated".to_string(),
+        "+set".to_string(),
+        "net_port".to_string(),
+        instance.port.to_string(),
+    ];
+    if !instance.server_cfg.is_empty() {
+        args.push("+exec".to_string());
+        args.push(instance.server_cfg.clone());
+    }
+    args.extend(crate::misc::split_args(&instance.args));
+    args
+}
+
+/// Instance names are used as file names, names and ports must be unique
+pub fn validate(instances: &[ServerInstance]) -> Result<(), String> {
+    for (i, instance) in instances.iter().enumerate() {
+        if instance.name.is_empty()
+            || !instance
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(format!(
+                "Invalid instance name '{}', use letters, digits, - and _",
+                instance.name
+            ));
+        }
+        if instance.client.is_empty() {
+            return Err(format!("Instance '{}' has no client set", instance.name));
+        }
+        if instance.port == 0 {
+            return Err(format!("Instance '{}' has no port set", instance.name));
+        }
+        for other in &instances[..i] {
+            if other.name == instance.name {
+                return Err(format!("Instance name '{}' is used twice", instance.name));
+            }
+            if other.port == instance.port {
+                return Err(format!(
+                    "Instances '{}' and '{}' use the same port {}",
+                    other.name, instance.name, instance.port
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// All instances, or only the one called `name`
+pub fn select<'a>(
+    instances: &'a [ServerInstance],
+    name: Option<&str>,
+) -> Result<Vec<&'a ServerInstance>, String> {
+    match name {
+        None => Ok(instances.iter().collect()),
+        Some(name) => instances
+            .iter()
+            .find(|instance| instance.name == name)
+            .map(|instance| vec![instance])
+            .ok_or_else(|| format!("No instance called '{name}' in the config")),
+    }
+}
+
+#[cfg(unix)]
+fn is_alive(pid: u32) -> bool {
+    // SAFETY: signal 0 only checks whether the process exists
+    unsafe { libc::kill(pid as libc::pid_t, 0) == 0 }
+}
+
+#[cfg(windows)]
+fn is_alive(pid: u32) -> bool {
+    std::process::Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {pid}"), "/NH"])
+        .output()
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains(&pid.to_string()))
+}
+
+/// State of the instance, `None` if it was never started or its supervisor is gone
+pub fn running_state(files: &InstanceFiles) -> Option<InstanceState> {
+    files.read_state().filter(|state| {
+        matches!(
+            state.status,
+            InstanceStatus::Starting
+                | InstanceStatus::Running
+                | InstanceStatus::Restarting
+                | InstanceStatus::Updating
+        ) && is_alive(state.pid)
+    })
+}
+
+/// Print the state of every instance, with map and players of the running ones
+pub fn print_status(instances: &[&ServerInstance], install_path: &Path) {
+    let dir = state_dir(install_path);
+    let states: Vec<_> = instances
+        .iter()
+        .map(|instance| {
+            let files = InstanceFiles::new(&dir, &instance.name);
+            (files.read_state(), running_state(&files))
+        })
+        .collect();
+
+    let addresses: Vec<SocketAddr> = instances
+        .iter()
+        .zip(&states)
+        .filter(|(_, (_, running))| running.is_some())
+        .map(|(instance, _)| SocketAddr::from(([127, 0, 0, 1], instance.port)))
+        .collect();
+    let live = servers::query_status(&addresses, Duration::from_secs(1));
+    let now = crate::cache::unix_time();
+
+    println!(
+        "{:<16} {:<10} {:>5}  {:<10} {:>8}  {:<12} {:<16} {:>7}",
+        "Name", "Client", "Port", "Status", "Restarts", "Started", "Map", "Players"
+    );
+    for (instance, (state, running)) in instances.iter().zip(&states) {
+        let status = match (state, running) {
+            (_, Some(running)) => format!("{:?}", running.status).to_lowercase(),
+            // the supervisor died without writing its final state
+            (Some(state), None)
+                if !matches!(
+                    state.status,
+                    InstanceStatus::Stopped | InstanceStatus::Crashed
+                ) =>
+            {
+                "dead".to_string()
+            }
+            (Some(state), None) => format!("{:?}", state.status).to_lowercase(),
+            (None, None) => "never run".to_string(),
+        };
+        let info = live
+            .iter()
+            .find(|info| info.address.port() == instance.port);
+        println!(
+            "{:<16} {:<10} {:>5}  {:<10} {:>8}  {:<12} {:<16} {:>7}",
+            instance.name,
+            instance.client,
+            instance.port,
+            status,
+            state.as_ref().map_or(0, |state| state.restarts),
+            state
+                .as_ref()
+                .map(|state| servers::format_age(now.saturating_sub(state.started)))
+                .unwrap_or_default(),
+            info.map(|info| info.map.as_str()).unwrap_or_default(),
+            info.map(|info| format!("{}/{}", info.clients, info.max_clients))
+                .unwrap_or_default(),
+        );
+    }
+}
+
+/// Ask the supervisors of the instances to stop their servers
+pub fn stop(instances: &[&ServerInstance], install_path: &Path) {
+    let dir = state_dir(install_path);
+    for instance in instances {
+        let files = InstanceFiles::new(&dir, &instance.name);
+        if running_state(&files).is_none() {
+            crate::println_info!("{} is not running", instance.name);
+            continue;
+        }
+        match files.request_stop() {
+            Ok(()) => crate::println_info!("Requested {} to stop", instance.name),
+            Err(error) => crate::println_error!("Failed to stop {}: {error}", instance.name),
+        }
+    }
+}
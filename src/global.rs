@@ -11,6 +11,8 @@ use crate::cdn::{Hosts, Server};
 pub const GH_OWNER: &str = "alterware";
 pub const GH_REPO: &str = "alterware-launcher";
 pub const DEFAULT_MASTER: &str = "https://cdn.alterware.ovh";
+/// Master server listing game servers for the server browser
+pub const DEFAULT_SERVER_MASTER: &str = "master.alterware.dev:20810";
 
 pub const CDN_HOSTS: [Server; 1] = [Server::new("cdn.alterware.ovh")];
 
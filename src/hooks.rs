@@ -0,0 +1,142 @@
+use crate::structs::Hooks;
+use once_cell::sync::Lazy;
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Mutex,
+};
+
+static HOOKS: Lazy<Mutex<Hooks>> = Lazy::new(|| Mutex::new(Hooks::default()));
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Phase {
+    PreUpdate,
+    PostUpdate,
+    PreLaunch,
+    PostExit,
+}
+
+impl Phase {
+    pub fn name(self) -> &'static str {
+        match self {
+            Phase::PreUpdate => "pre_update",
+            Phase::PostUpdate => "post_update",
+            Phase::PreLaunch => "pre_launch",
+            Phase::PostExit => "post_exit",
+        }
+    }
+
+    fn command(self, hooks: &Hooks) -> &str {
+        match self {
+            Phase::PreUpdate => &hooks.pre_update,
+            Phase::PostUpdate => &hooks.post_update,
+            Phase::PreLaunch => &hooks.pre_launch,
+            Phase::PostExit => &hooks.post_exit,
+        }
+    }
+}
+
+/// What the launcher is doing, passed to hooks as `ALTERWARE_*` environment variables
+#[derive(Debug, Clone, Default)]
+pub struct Context {
+    pub engine: String,
+    pub client: String,
+    pub install_path: PathBuf,
+    pub changed_files: Vec<String>,
+    pub exit_code: Option<i32>,
+}
+
+impl Context {
+    pub fn new(engine: &str, client: &str, install_path: &Path) -> Self {
+        Self {
+            engine: engine.to_string(),
+            client: client.to_string(),
+            install_path: install_path.to_path_buf(),
+            ..Default::default()
+        }
+    }
+
+    /// Context for launching `client`, the engine is looked up in the stored game data
+    pub fn for_client(client: &str, install_path: &Path) -> Self {
+        let engine = crate::cache::engine_of(client).unwrap_or_default();
+        Self::new(&engine, client, install_path)
+    }
+
+    pub fn env(&self, phase: Phase) -> Vec<(String, String)> {
+        vec![
+            ("ALTERWARE_HOOK".to_string(), phase.name().to_string()),
+            ("ALTERWARE_ENGINE".to_string(), self.engine.clone()),
+            ("ALTERWARE_CLIENT".to_string(), self.client.clone()),
+            (
+                "ALTERWARE_INSTALL_PATH".to_string(),
+                self.install_path.to_string_lossy().into_owned(),
+            ),
+            (
+                "ALTERWARE_CHANGED_FILES".to_string(),
+                self.changed_files.join("\n"),
+            ),
+            (
+                "ALTERWARE_EXIT_CODE".to_string(),
+                self.exit_code
+                    .map(|code| code.to_string())
+                    .unwrap_or_default(),
+            ),
+        ]
+    }
+}
+
+/// Hooks from the config
+pub fn configure(hooks: Hooks) {
+    *HOOKS.lock().unwrap() = hooks;
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+/// Run the hook of `phase` if one is configured, failing if it exits with a non-zero status
+pub fn run(phase: Phase, context: &Context) -> Result<(), String> {
+    let command = phase.command(&HOOKS.lock().unwrap()).to_string();
+    if command.is_empty() {
+        return Ok(());
+    }
+
+    crate::println_info!("Running {} hook", phase.name());
+    let mut hook = shell(&command);
+    hook.envs(context.env(phase));
+    if context.install_path.is_dir() {
+        hook.current_dir(&context.install_path);
+    }
+    let status = hook
+        .status()
+        .map_err(|e| format!("Failed to run {} hook: {e}", phase.name()))?;
+    if !status.success() {
+        return Err(format!("{} hook exited with {status}", phase.name()));
+    }
+    Ok(())
+}
+
+/// Run a pre hook, ending the launcher if it fails
+pub fn run_or_abort(phase: Phase, context: &Context) {
+    if let Err(error) = run(phase, context) {
+        crate::println_error!("{error}, aborting");
+        std::process::exit(1);
+    }
+}
+
+/// Run a post hook, failures are only reported
+pub fn run_or_report(phase: Phase, context: &Context) {
+    if let Err(error) = run(phase, context) {
+        crate::println_error!("{error}");
+    }
+}
@@ -64,6 +64,7 @@ pub async fn download_file_progress(
         file.write_all(&chunk)
             .map_err(|e| format!("Error while writing to file: {e}"))?;
 
+        crate::metrics::add_downloaded(chunk.len() as u64);
         downloaded = min(downloaded + (chunk.len() as u64), total_size);
         pb.set_position(downloaded);
     }
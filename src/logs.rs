@@ -0,0 +1,204 @@
+use crate::structs::GameLogConfig;
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread::JoinHandle,
+};
+
+/// Where lines copied into a log are also written to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Echo {
+    Off,
+    Stdout,
+    Stderr,
+}
+
+/// Log file that is renamed to `<name>.1`, `<name>.2`, ... once it grows past `max_size`
+pub struct RotatingLog {
+    path: PathBuf,
+    max_size: u64,
+    keep: u32,
+    file: File,
+    size: u64,
+}
+
+fn rotated_path(path: &Path, index: u32) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{index}"));
+    PathBuf::from(name)
+}
+
+impl RotatingLog {
+    pub fn open(path: &Path, max_size: u64, keep: u32) -> io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_size,
+            keep,
+            file,
+            size,
+        })
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.keep == 0 {
+            self.file = File::create(&self.path)?;
+        } else {
+            let _ = fs::remove_file(rotated_path(&self.path, self.keep));
+            for index in (1..self.keep).rev() {
+                let from = rotated_path(&self.path, index);
+                if from.exists() {
+                    fs::rename(&from, rotated_path(&self.path, index + 1))?;
+                }
+            }
+            fs::rename(&self.path, rotated_path(&self.path, 1))?;
+            self.file = File::create(&self.path)?;
+        }
+        self.size = 0;
+        Ok(())
+    }
+
+    /// Append a line, rotating first if it would push the file past the size limit
+    pub fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        let len = line.len() as u64 + 1;
+        if self.size > 0 && self.size + len > self.max_size {
+            self.rotate()?;
+        }
+        self.file.write_all(line)?;
+        self.file.write_all(b"\n")?;
+        self.size += len;
+        Ok(())
+    }
+}
+
+/// Copy lines from a child's output into the log on a background thread
+pub fn pipe_lines<R: Read + Send + 'static>(
+    reader: R,
+    log: Arc<Mutex<RotatingLog>>,
+    echo: Echo,
+) -> JoinHandle<()> {
+    std::thread::spawn(move || {
+        // keep draining after a write error so the child doesn't block on a full pipe
+        let mut failed = false;
+        for line in BufReader::new(reader).split(b'\n') {
+            let Ok(mut line) = line else {
+                break;
+            };
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            let _ = match echo {
+                Echo::Off => Ok(()),
+                Echo::Stdout => writeln_raw(&mut io::stdout().lock(), &line),
+                Echo::Stderr => writeln_raw(&mut io::stderr().lock(), &line),
+            };
+            if failed {
+                continue;
+            }
+            if let Err(error) = log.lock().unwrap().write_line(&line) {
+                error!("Failed to write log: {error}");
+                failed = true;
+            }
+        }
+    })
+}
+
+/// Log file of the launcher itself, cleared on every start except for server management commands
+#[cfg(windows)]
+pub fn launcher_log() -> PathBuf {
+    std::env::current_exe()
+        .unwrap_or(PathBuf::from("alterware-launcher"))
+        .with_extension("log")
+}
+
+/// Log file of the launcher itself, cleared on every start except for server management commands.
+///
+/// `/var/log` is only writable when running as a service, users log to the data directory.
+#[cfg(unix)]
+pub fn launcher_log() -> PathBuf {
+    let system = PathBuf::from("/var/log/alterware-launcher.log");
+    if OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&system)
+        .is_ok()
+    {
+        return system;
+    }
+
+    match crate::misc::data_dir() {
+        Some(dir) if fs::create_dir_all(&dir).is_ok() => dir.join("alterware-launcher.log"),
+        _ => system,
+    }
+}
+
+fn writeln_raw(out: &mut impl Write, line: &[u8]) -> io::Result<()> {
+    out.write_all(line)?;
+    out.write_all(b"\n")?;
+    out.flush()
+}
+
+/// Directory of the game session logs, next to the launcher's own log
+#[cfg(windows)]
+pub fn game_log_dir() -> Option<PathBuf> {
+    std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join("logs")))
+}
+
+/// Directory of the game session logs, `$XDG_DATA_HOME/alterware-launcher/logs`
+#[cfg(unix)]
+pub fn game_log_dir() -> Option<PathBuf> {
+    crate::misc::data_dir().map(|dir| dir.join("logs"))
+}
+
+/// Session logs of `client` in `dir`, oldest first
+pub fn sessions(dir: &Path, client: &str) -> Vec<PathBuf> {
+    let prefix = format!("{client}-");
+    let mut sessions: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    path.file_name()
+                        .and_then(|name| name.to_str())
+                        .and_then(|name| name.strip_prefix(&prefix))
+                        .and_then(|rest| rest.strip_suffix(".log"))
+                        .is_some_and(|time| time.chars().all(|c| c.is_ascii_digit()))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    // the unix time in the name keeps the same width for a long time
+    sessions.sort();
+    sessions
+}
+
+/// Start a new session log for `client` in `dir`, removing the oldest sessions past the limit.
+///
+/// A session is rotated once, so it takes up at most twice `max_size`.
+pub fn open_session(
+    dir: &Path,
+    client: &str,
+    cfg: &GameLogConfig,
+) -> io::Result<(PathBuf, RotatingLog)> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{client}-{}.log", crate::cache::unix_time()));
+    let old = sessions(dir, client);
+    let keep = (cfg.sessions as usize).saturating_sub(1);
+    for session in old.iter().take(old.len().saturating_sub(keep)) {
+        if *session != path {
+            let _ = fs::remove_file(session);
+            let _ = fs::remove_file(rotated_path(session, 1));
+        }
+    }
+    let log = RotatingLog::open(&path, cfg.max_size, 1)?;
+    Ok((path, log))
+}
@@ -1,14 +1,32 @@
 mod cache;
 mod cdn;
 mod config;
+mod crash;
+#[cfg(unix)]
+mod desktop;
 mod extend;
+mod fleet;
 mod github;
 mod global;
+mod hooks;
 mod http;
 mod http_async;
+mod logs;
+mod metrics;
 mod misc;
+mod rcon;
+#[cfg(unix)]
+mod runner;
 mod self_update;
+mod servers;
+mod steam;
 mod structs;
+mod supervisor;
+#[cfg(unix)]
+mod systemd;
+mod updater;
+mod vdf;
+mod webhooks;
 
 #[cfg(test)]
 mod tests;
@@ -29,36 +47,24 @@ use std::{
     borrow::Cow,
     collections::HashMap,
     env, fs,
+    net::SocketAddr,
     path::{Path, PathBuf},
+    sync::{Arc, Mutex},
 };
-#[cfg(windows)]
-use steamlocate::SteamDir;
 
-#[cfg(windows)]
 fn get_installed_games(games: &Vec<Game>) -> Vec<(u32, PathBuf)> {
-    let mut installed_games = Vec::new();
-    let steamdir_result = SteamDir::locate();
-
-    let steamdir = match steamdir_result {
-        Ok(steamdir) => steamdir,
-        Err(error) => {
-            crate::println_error!("Error locating Steam: {error}");
-            return installed_games;
-        }
-    };
-
-    for game in games {
-        if let Ok(Some((app, library))) = steamdir.find_app(game.app_id) {
-            let game_path = library
-                .path()
-                .join("steamapps")
-                .join("common")
-                .join(&app.install_dir);
-            installed_games.push((game.app_id, game_path));
-        }
+    let libraries = steam::library_folders();
+    if libraries.is_empty() {
+        crate::println_error!("Error locating Steam: no Steam installation found");
+        return Vec::new();
     }
 
-    installed_games
+    games
+        .iter()
+        .filter_map(|game| {
+            steam::find_app(&libraries, game.app_id).map(|game_path| (game.app_id, game_path))
+        })
+        .collect()
 }
 
 #[cfg(windows)]
@@ -116,11 +122,88 @@ fn setup_desktop_links(path: &Path, game: &Game) {
 async fn auto_install(path: &Path, game: &Game<'_>) {
     setup_client_links(game, path);
     setup_desktop_links(path, game);
-    update(game, path, false, false, None).await;
+    update(game, game.client[0], path, false, false, None)
+        .await
+        .unwrap_or_else(update_failed);
 }
 
-#[cfg(windows)]
-async fn windows_launcher_install(games: &Vec<Game<'_>>) {
+#[cfg(unix)]
+fn setup_desktop_entries(path: &Path, game: &Game) {
+    println!("Create Desktop shortcut? (Y/n)");
+    let on_desktop = !misc::stdin().eq_ignore_ascii_case("n");
+
+    let launcher_path = env::current_exe().unwrap();
+    let installed_launcher = path.join(launcher_path.file_name().unwrap());
+    let launcher = if installed_launcher.exists() {
+        installed_launcher
+    } else {
+        launcher_path
+    };
+
+    match desktop::create_entries(&game.client, &launcher, path, on_desktop) {
+        Ok(created) => {
+            for entry in created {
+                info!("Created desktop entry {}", entry.display());
+            }
+        }
+        Err(error) => crate::println_error!("Error creating desktop entries.\n{error}"),
+    }
+}
+
+#[cfg(unix)]
+fn setup_steam_shortcuts(path: &Path, game: &Game, grid_dir: Option<&Path>) {
+    let user_dirs = steam::user_dirs();
+    if user_dirs.is_empty() {
+        crate::println_error!("No Steam user found, log in to Steam at least once.");
+        return;
+    }
+
+    let launcher = env::current_exe().unwrap();
+    let icon = desktop::install_icon()
+        .map_err(|error| warn!("Failed to install icon: {error}"))
+        .ok();
+    let shortcuts: Vec<steam::Shortcut> = game
+        .client
+        .iter()
+        .map(|c| steam::Shortcut {
+            app_name: c.to_string(),
+            exe: launcher.clone(),
+            start_dir: path.to_path_buf(),
+            launch_options: misc::join_args(&[
+                c.to_string(),
+                "--path".to_string(),
+                path.to_string_lossy().into_owned(),
+            ]),
+            icon: icon.clone(),
+        })
+        .collect();
+
+    println!("Steam overwrites its shortcuts on exit, make sure Steam is closed.");
+    for user_dir in user_dirs {
+        match steam::register_shortcuts(&user_dir, &shortcuts, grid_dir) {
+            Ok(0) => crate::println_info!(
+                "Shortcuts already registered for Steam user {}",
+                user_dir.cute_path()
+            ),
+            Ok(added) => crate::println_info!(
+                "Added {added} shortcut(s) for Steam user {}",
+                user_dir.cute_path()
+            ),
+            Err(error) => crate::println_error!("{error}"),
+        }
+    }
+    crate::println_info!("Restart Steam for the shortcuts to show up in your library.");
+}
+
+#[cfg(unix)]
+async fn auto_install(path: &Path, game: &Game<'_>) {
+    setup_desktop_entries(path, game);
+    update(game, game.client[0], path, false, false, None)
+        .await
+        .unwrap_or_else(update_failed);
+}
+
+async fn launcher_install(games: &Vec<Game<'_>>) {
     crate::println_info!(
         "{}",
         "No game specified/found. Checking for installed Steam games..".yellow()
@@ -148,14 +231,15 @@ async fn windows_launcher_install(games: &Vec<Game<'_>>) {
         }
 
         println!("Enter the ID of the game you want to install the AlterWare client for:");
-        let input: u32 = misc::stdin().parse().unwrap();
+        let input: u32 =
+            misc::stdin_number(|input| installed_games.iter().any(|(id, _)| id == input));
 
         for (id, path) in installed_games.iter() {
             if *id == input {
                 let game = games.iter().find(|&g| g.app_id == input).unwrap();
 
                 let launcher_path = env::current_exe().unwrap();
-                let target_path = path.join("alterware-launcher.exe");
+                let target_path = path.join(launcher_path.file_name().unwrap());
 
                 if launcher_path != target_path {
                     fs::copy(launcher_path, &target_path).unwrap();
@@ -163,7 +247,10 @@ async fn windows_launcher_install(games: &Vec<Game<'_>>) {
                 }
                 auto_install(path, game).await;
                 crate::println_info!("Installation complete.");
+                #[cfg(windows)]
                 crate::println_info!("Please use one of the shortcuts (on your Desktop or in the game folder) to play.");
+                #[cfg(unix)]
+                crate::println_info!("Please use one of the shortcuts (in your application menu or on your Desktop) to play.");
                 crate::println_info!(
                     "Alternatively run the launcher again from the game folder {}",
                     target_path.display()
@@ -194,15 +281,14 @@ fn total_download_size(cdn_info: &Vec<CdnFile>, remote_dir: &str) -> u64 {
     size
 }
 
-async fn update_dir(
+/// Files of `remote_dir` that are missing or differ from the install, hashes of up to date files are added to `hashes`
+fn outdated_files(
     cdn_info: &Vec<CdnFile>,
     remote_dir: &str,
     dir: &Path,
     hashes: &mut HashMap<String, String>,
     pb: &ProgressBar,
-) {
-    misc::pb_style_download(pb, false);
-
+) -> Vec<CdnFile> {
     let remote_dir_pre = format!("{remote_dir}/");
 
     let mut files_to_download: Vec<CdnFile> = vec![];
@@ -234,6 +320,21 @@ async fn update_dir(
             files_to_download.push(file.clone());
         }
     }
+    files_to_download
+}
+
+/// Download outdated or missing files of `remote_dir`, returning their names and size
+async fn update_dir(
+    cdn_info: &Vec<CdnFile>,
+    remote_dir: &str,
+    dir: &Path,
+    hashes: &mut HashMap<String, String>,
+    pb: &ProgressBar,
+) -> Result<(Vec<String>, u64), String> {
+    misc::pb_style_download(pb, false);
+
+    let remote_dir_pre = format!("{remote_dir}/");
+    let files_to_download = outdated_files(cdn_info, remote_dir, dir, hashes, pb);
 
     if files_to_download.is_empty() {
         let msg = format!(
@@ -243,12 +344,17 @@ async fn update_dir(
         );
         pb.println(&msg);
         info!("{msg}");
-        return;
+        return Ok((Vec::new(), 0));
     }
+    let download_size = total_download_size(&files_to_download, remote_dir);
+    let changed_files: Vec<String> = files_to_download
+        .iter()
+        .map(|file| file.name.replace(&remote_dir_pre, ""))
+        .collect();
     let msg = format!(
         "{}Downloading outdated or missing files for {remote_dir}, {}",
         misc::prefix("info"),
-        misc::human_readable_bytes(total_download_size(&files_to_download, remote_dir))
+        misc::human_readable_bytes(download_size)
     );
     pb.println(&msg);
     info!("{msg}");
@@ -260,15 +366,17 @@ async fn update_dir(
         let file_path = dir.join(file_name);
         if let Some(parent) = file_path.parent() {
             if !parent.exists() {
-                fs::create_dir_all(parent).unwrap();
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create {}: {e}", parent.cute_path()))?;
             }
         }
 
-        // Prompt user to retry downloads if they fail
-        let mut download_complete = false;
+        // Prompt user to retry downloads if they fail, without a terminal retry a few times
+        let interactive = std::io::IsTerminal::is_terminal(&std::io::stdin());
+        let mut attempt = 0;
         let mut bust_cache = false;
-        let mut local_hash = String::default();
-        while !download_complete {
+        let local_hash = loop {
+            attempt += 1;
             let url = format!("{}/{}", MASTER_URL.lock().unwrap(), file.name);
             let url = if bust_cache {
                 bust_cache = false;
@@ -283,25 +391,23 @@ async fn update_dir(
             {
                 let file_name = file_path.clone().cute_path();
                 println_error!("{err}");
-                println!("Failed to download file {file_name}, retry? (Y/n)");
-                let input = misc::stdin().to_ascii_lowercase();
-                if input == "n" {
+                let question = format!("Failed to download file {file_name}, retry?");
+                if !misc::retry_download(&question, attempt, interactive).await {
                     error!("Download for file {file_name} failed with {err}");
-                    panic!("{err}");
-                } else {
-                    warn!(
-                        "Download for file {file_name} failed with {err} user chose to retry download"
-                    );
+                    return Err(format!("Download for file {file_name} failed with {err}"));
                 }
+                warn!("Download for file {file_name} failed with {err}, retrying download");
+                continue;
             };
 
-            local_hash = file_path.get_blake3().unwrap().to_lowercase();
+            let local_hash = file_path
+                .get_blake3()
+                .map_err(|e| format!("Failed to hash {}: {e}", file_path.cute_path()))?
+                .to_lowercase();
             let remote = file.blake3.to_lowercase();
             if local_hash != remote && !file_path.ends_with(".html") {
                 println_error!("Downloaded file hash does not match remote!\nRemote {remote}, local {local_hash}, {}\nIf this issue persists please try again in 15 minutes.", file_path.cute_path());
-                println!("Retry download? (Y/n)");
-                let input = misc::stdin().to_ascii_lowercase();
-                if input != "n" {
+                if misc::retry_download("Retry download?", attempt, interactive).await {
                     println_info!(
                         "Retrying download for {} due to hash mismatch",
                         file_path.cute_path()
@@ -309,10 +415,16 @@ async fn update_dir(
                     bust_cache = true;
                     continue;
                 }
+                if !interactive {
+                    return Err(format!(
+                        "Downloaded file {} does not match the remote hash",
+                        file_path.cute_path()
+                    ));
+                }
             }
 
-            download_complete = true;
-        }
+            break local_hash;
+        };
 
         hashes.insert(file_name.to_owned(), local_hash);
 
@@ -325,28 +437,23 @@ async fn update_dir(
         }
     }
     misc::pb_style_download(pb, false);
+    Ok((changed_files, download_size))
 }
 
+/// Update the install, sending the update events and running the update hooks around it
 async fn update(
     game: &Game<'_>,
+    client: &str,
     dir: &Path,
     bonus_content: bool,
     force: bool,
     ignore_required_files: Option<bool>,
-) {
+) -> Result<(), String> {
     info!("Starting update for game engine: {}", game.engine);
     info!("Update path: {}", dir.display());
     debug!("Bonus content: {}, Force update: {}", bonus_content, force);
 
     let ignore_required_files = ignore_required_files.unwrap_or(false);
-
-    let res =
-        http_async::get_body_string(format!("{}/files.json", MASTER_URL.lock().unwrap()).as_str())
-            .await
-            .unwrap();
-    debug!("Retrieved files.json from server");
-    let cdn_info: Vec<CdnFile> = serde_json::from_str(&res).unwrap();
-
     if !ignore_required_files && !game.required_files_exist(dir) {
         error!("Critical game files missing. Required files check failed.");
         println!(
@@ -357,6 +464,70 @@ async fn update(
         std::process::exit(0);
     }
 
+    hooks::run_or_abort(
+        hooks::Phase::PreUpdate,
+        &hooks::Context::new(game.engine, client, dir),
+    );
+    webhooks::send(webhooks::Event::UpdateStarted {
+        engine: game.engine.to_string(),
+        path: dir.to_string_lossy().into_owned(),
+    });
+
+    let result = update_files(game, dir, bonus_content, force).await;
+    metrics::update_finished(result.is_ok());
+    let (changed_files, bytes) = result.clone().unwrap_or_default();
+    webhooks::send(webhooks::Event::UpdateFinished {
+        engine: game.engine.to_string(),
+        path: dir.to_string_lossy().into_owned(),
+        success: result.is_ok(),
+        files_changed: changed_files.len(),
+        bytes,
+        error: result.as_ref().err().cloned(),
+    });
+    hooks::run_or_report(
+        hooks::Phase::PostUpdate,
+        &hooks::Context {
+            changed_files,
+            exit_code: Some(i32::from(result.is_err())),
+            ..hooks::Context::new(game.engine, client, dir)
+        },
+    );
+    result.map(|_| ())
+}
+
+/// End the launcher after a failed update, the failure was already reported by `update`
+fn update_failed(error: String) {
+    crate::println_error!("Update failed: {error}");
+    webhooks::flush();
+    misc::stdin();
+    std::process::exit(1);
+}
+
+/// Supervised servers keep running on the installed files when their update fails,
+/// the failure shows up in the metrics and webhooks instead of taking them down
+fn update_failed_supervised(error: String, exe: &Path) {
+    if exe.is_file() {
+        crate::println_error!("Update failed: {error}, starting with the installed files");
+    } else {
+        update_failed(error);
+    }
+}
+
+/// Download the files of the game and its bonus content, returning the changed files and their size
+async fn update_files(
+    game: &Game<'_>,
+    dir: &Path,
+    bonus_content: bool,
+    force: bool,
+) -> Result<(Vec<String>, u64), String> {
+    let res =
+        http_async::get_body_string(format!("{}/files.json", MASTER_URL.lock().unwrap()).as_str())
+            .await
+            .map_err(|e| format!("Failed to fetch files.json: {e}"))?;
+    debug!("Retrieved files.json from server");
+    let cdn_info: Vec<CdnFile> =
+        serde_json::from_str(&res).map_err(|e| format!("Failed to parse files.json: {e}"))?;
+
     let old_files = [".sha-sums", ".iw4xrevision"];
     for f in old_files {
         if dir.join(f).exists() {
@@ -375,63 +546,23 @@ async fn update(
         cache::get_cache(dir)
     };
 
-    for entry in game.rename.iter() {
-        let file_path = dir.join(entry.0);
-        let new_path = dir.join(entry.1);
-        if file_path.exists() {
-            if fs::rename(&file_path, &new_path).is_err() {
-                println!(
-                    "{}Couldn't rename {} -> {}",
-                    misc::prefix("error"),
-                    file_path.cute_path(),
-                    new_path.cute_path()
-                );
-            } else {
-                println!(
-                    "{}{} -> {}",
-                    misc::prefix("renamed"),
-                    file_path.cute_path(),
-                    new_path.cute_path()
-                );
-            }
-        }
-    }
+    game.rename_files(dir);
 
     let pb = ProgressBar::new(0);
-    update_dir(&cdn_info, game.engine, dir, &mut cache.hashes, &pb).await;
+    let (mut changed_files, mut bytes) =
+        update_dir(&cdn_info, game.engine, dir, &mut cache.hashes, &pb).await?;
 
     if bonus_content && !game.bonus.is_empty() {
         for bonus in game.bonus.iter() {
-            update_dir(&cdn_info, bonus, dir, &mut cache.hashes, &pb).await;
+            let (files, size) = update_dir(&cdn_info, bonus, dir, &mut cache.hashes, &pb).await?;
+            changed_files.extend(files);
+            bytes += size;
         }
     }
 
     pb.finish();
 
-    for f in game.delete.iter() {
-        let file_path = dir.join(f);
-        if file_path.is_file() {
-            if fs::remove_file(&file_path).is_err() {
-                println!(
-                    "{}Couldn't delete {}",
-                    misc::prefix("error"),
-                    file_path.cute_path()
-                );
-            } else {
-                println!("{}{}", misc::prefix("removed"), file_path.cute_path());
-            }
-        } else if file_path.is_dir() {
-            if fs::remove_dir_all(&file_path).is_err() {
-                println!(
-                    "{}Couldn't delete {}",
-                    misc::prefix("error"),
-                    file_path.cute_path()
-                );
-            } else {
-                println!("{}{}", misc::prefix("removed"), file_path.cute_path());
-            }
-        }
-    }
+    game.delete_files(dir);
 
     cache::save_cache(dir, cache);
 
@@ -444,6 +575,9 @@ async fn update(
         game.engine.to_string(),
         game.client.iter().map(|s| s.to_string()).collect(),
     );
+    stored_data
+        .app_ids
+        .insert(game.engine.to_string(), game.app_id);
 
     if let Err(e) = cache::store_game_data(&stored_data) {
         println!(
@@ -452,24 +586,36 @@ async fn update(
             e
         );
     }
+    Ok((changed_files, bytes))
 }
 
 #[cfg(windows)]
-fn launch(file_path: &PathBuf, args: &str) {
+fn game_command(
+    file_path: &Path,
+    args: &[String],
+    cfg: &Config,
+    _app_id: Option<u32>,
+) -> GameCommand {
+    GameCommand {
+        program: file_path.to_path_buf(),
+        args: args.to_vec(),
+        env: cfg.env.clone().into_iter().collect(),
+        dir: file_path.parent().unwrap().to_path_buf(),
+    }
+}
+
+#[cfg(windows)]
+fn launch(file_path: &Path, args: &[String], cfg: &Config, app_id: Option<u32>) {
+    let args_display = misc::join_args(&misc::redact_words(args));
     info!(
         "Launching game on Windows: {} {}",
         file_path.display(),
-        args
+        args_display
     );
     println!("\n\nJoin the AlterWare Discord server:\nhttps://discord.gg/2ETE8engZM\n\n");
-    crate::println_info!("Launching {} {args}", file_path.display());
-    let exit_status = std::process::Command::new(file_path)
-        .args(args.trim().split(' '))
-        .current_dir(file_path.parent().unwrap())
-        .spawn()
-        .expect("Failed to launch the game")
-        .wait()
-        .expect("Failed to wait for the game process to finish");
+    crate::println_info!("Launching {} {args_display}", file_path.display());
+    let started = std::time::SystemTime::now();
+    let (exit_status, game_log) = run_game(file_path, args, cfg, app_id);
 
     if exit_status.success() {
         info!("Game exited successfully with status: {}", exit_status);
@@ -479,48 +625,363 @@ fn launch(file_path: &PathBuf, args: &str) {
 
     crate::println_error!("Game exited with {exit_status}");
     if !exit_status.success() {
+        if let Some(game_log) = &game_log {
+            crate::println_info!("Game output was saved to {}", game_log.display());
+        }
+        report_crash(file_path, &exit_status, started, game_log.as_deref(), cfg);
+        misc::stdin();
+    }
+}
+
+/// Collect a crash report for a game that exited with an error and print where it is
+fn report_crash(
+    file_path: &Path,
+    exit_status: &std::process::ExitStatus,
+    started: std::time::SystemTime,
+    game_log: Option<&Path>,
+    cfg: &Config,
+) {
+    let client = file_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let launcher_log = logs::launcher_log();
+    let crash = crash::Crash {
+        client: &client,
+        install_path: file_path.parent().unwrap_or(Path::new(".")),
+        exit_status: exit_status.to_string(),
+        started,
+        game_log,
+        launcher_log: Some(&launcher_log),
+    };
+    match crash::collect(&crash, cfg) {
+        Ok(path) => crate::println_info!(
+            "Crash report saved to {}, attach it when asking for help",
+            path.display()
+        ),
+        Err(error) => crate::println_error!("Failed to create crash report: {error}"),
+    }
+}
+
+/// Start a session log for the game's output, `None` if disabled or it couldn't be created
+fn open_game_log(client: &str, cfg: &Config) -> Option<(PathBuf, Arc<Mutex<logs::RotatingLog>>)> {
+    if cfg.game_log.sessions == 0 {
+        return None;
+    }
+    let dir = logs::game_log_dir()?;
+    match logs::open_session(&dir, client, &cfg.game_log) {
+        Ok((path, log)) => {
+            info!("Writing game output to {}", path.display());
+            Some((path, Arc::new(Mutex::new(log))))
+        }
+        Err(error) => {
+            warn!("Failed to create game log in {}: {error}", dir.display());
+            None
+        }
+    }
+}
+
+/// Start the game and wait for it to exit, sending launch and exit events.
+///
+/// Returns the exit status and the session log the game's output was written to.
+fn run_game(
+    file_path: &Path,
+    args: &[String],
+    cfg: &Config,
+    app_id: Option<u32>,
+) -> (std::process::ExitStatus, Option<PathBuf>) {
+    let client = file_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let install_path = file_path.parent().unwrap_or(Path::new("."));
+    let mut hook_context = hooks::Context::for_client(&client, install_path);
+    hooks::run_or_abort(hooks::Phase::PreLaunch, &hook_context);
+
+    let session_log = open_game_log(&client, cfg);
+    let mut command = game_command(file_path, args, cfg, app_id).command();
+    if session_log.is_some() {
+        command
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped());
+    }
+    let mut child = command.spawn().expect("Failed to launch the game");
+    let mut pipes = Vec::new();
+    if let Some((_, log)) = &session_log {
+        let interactive = std::io::IsTerminal::is_terminal(&std::io::stdout());
+        let echo = |echo| if interactive { echo } else { logs::Echo::Off };
+        if let Some(stdout) = child.stdout.take() {
+            pipes.push(logs::pipe_lines(
+                stdout,
+                log.clone(),
+                echo(logs::Echo::Stdout),
+            ));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            pipes.push(logs::pipe_lines(
+                stderr,
+                log.clone(),
+                echo(logs::Echo::Stderr),
+            ));
+        }
+    }
+    webhooks::send(webhooks::Event::GameLaunched {
+        client: client.clone(),
+        path: install_path.to_string_lossy().into_owned(),
+    });
+
+    let exit_status = child
+        .wait()
+        .expect("Failed to wait for the game process to finish");
+    // processes like wineserver can inherit the pipes and outlive the game,
+    // so the last lines only get a moment to arrive
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(2);
+    while pipes.iter().any(|pipe| !pipe.is_finished()) && std::time::Instant::now() < deadline {
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+    webhooks::send(webhooks::Event::GameExited {
+        client,
+        success: exit_status.success(),
+        exit_code: exit_status.code(),
+        status: exit_status.to_string(),
+    });
+    hook_context.exit_code = exit_status.code();
+    hooks::run_or_report(hooks::Phase::PostExit, &hook_context);
+    webhooks::flush();
+    (exit_status, session_log.map(|(path, _)| path))
+}
+
+/// Resolve the runner, environment and prefix the game is started with
+#[cfg(unix)]
+fn game_command(
+    file_path: &Path,
+    args: &[String],
+    cfg: &Config,
+    app_id: Option<u32>,
+) -> GameCommand {
+    let runner = runner::Runner::from_config(cfg).unwrap_or_else(|error| {
+        crate::println_error!("{error}");
         misc::stdin();
+        std::process::exit(1);
+    });
+    let command = runner::launch_command(cfg, &runner, file_path, args);
+    // the launch command can hold passwords and the environment tokens, neither is logged
+    info!(
+        "Launch command: {}",
+        misc::join_args(&misc::redact_words(&command))
+    );
+
+    let launch_env = runner::launch_env(cfg, &runner, file_path, app_id);
+    let env_keys: Vec<&str> = launch_env.iter().map(|(key, _)| key.as_str()).collect();
+    info!("Launch env: {}", env_keys.join(" "));
+
+    if runner == runner::Runner::Umu {
+        let umu_env: Vec<String> = launch_env
+            .iter()
+            .filter(|(k, _)| runner::UMU_ENV_KEYS.contains(&k.as_str()))
+            .map(|(k, v)| format!("{k}={v}"))
+            .collect();
+        crate::println_info!("umu-run environment: {}", umu_env.join(" "));
+    }
+
+    if let Err(error) = runner::init_prefix(&runner, &launch_env) {
+        crate::println_error!("{error}");
+    }
+
+    if command.is_empty() {
+        crate::println_error!("Launch command is empty, check launch_command in the config.");
+        misc::stdin();
+        std::process::exit(1);
+    }
+
+    if runner != runner::Runner::Native {
+        let runner_name = runner.name();
+        println!("Found {runner_name}, launching game using {runner_name}.\nIf you run into issues or want to launch a different way, run {} manually.", file_path.display());
+    }
+
+    GameCommand {
+        program: PathBuf::from(&command[0]),
+        args: command[1..].to_vec(),
+        env: launch_env,
+        dir: file_path.parent().unwrap().to_path_buf(),
     }
 }
 
 #[cfg(unix)]
-fn launch(file_path: &PathBuf, args: &str) {
+fn launch(file_path: &Path, args: &[String], cfg: &Config, app_id: Option<u32>) {
     println!("\n\nJoin the AlterWare Discord server:\nhttps://discord.gg/2ETE8engZM\n\n");
-    crate::println_info!("Launching {} {args}", file_path.display());
-
-    let launcher = if misc::is_program_in_path("umu-run") {
-        Some("umu-run")
-    } else if misc::is_program_in_path("wine") {
-        Some("wine")
-    } else {
-        None
-    };
+    crate::println_info!(
+        "Launching {} {}",
+        file_path.display(),
+        misc::join_args(&misc::redact_words(args))
+    );
 
-    let exit_status = if let Some(launcher) = launcher {
-        println!("Found {launcher}, launching game using {launcher}.\nIf you run into issues or want to launch a different way, run {} manually.", file_path.display());
-        std::process::Command::new(launcher)
-            .args([file_path.to_str().unwrap(), args.trim()])
-            .current_dir(file_path.parent().unwrap())
-            .spawn()
-            .expect("Failed to launch the game")
-            .wait()
-            .expect("Failed to wait for the game process to finish")
-    } else {
-        std::process::Command::new(file_path)
-            .args(args.trim().split(' '))
-            .current_dir(file_path.parent().unwrap())
-            .spawn()
-            .expect("Failed to launch the game")
-            .wait()
-            .expect("Failed to wait for the game process to finish")
-    };
+    let started = std::time::SystemTime::now();
+    let (exit_status, game_log) = run_game(file_path, args, cfg, app_id);
 
     crate::println_error!("Game exited with {exit_status}");
     if !exit_status.success() {
+        if let Some(game_log) = &game_log {
+            crate::println_info!("Game output was saved to {}", game_log.display());
+        }
+        report_crash(file_path, &exit_status, started, game_log.as_deref(), cfg);
         misc::stdin();
     }
 }
 
+/// Update the installs of all instances once, then start and supervise every instance.
+///
+/// Without `games` (offline mode) the instances are started without updating.
+/// With `daemon` the installs are checked for updates until all instances have stopped.
+async fn run_fleet(
+    games: Option<&[Game<'_>]>,
+    install_path: &Path,
+    cfg: &Config,
+    ignore_required_files: bool,
+    daemon: bool,
+) {
+    if cfg.instances.is_empty() {
+        crate::println_error!("No instances configured, add them to instances in the config.");
+        std::process::exit(1);
+    }
+    if let Err(error) = fleet::validate(&cfg.instances) {
+        crate::println_error!("{error}");
+        std::process::exit(1);
+    }
+    serve_metrics(cfg);
+
+    let mut updated: Vec<PathBuf> = Vec::new();
+    let mut commands = Vec::new();
+    for instance in &cfg.instances {
+        let path = fleet::instance_path(instance, install_path);
+        let game = games.and_then(|games| {
+            games
+                .iter()
+                .find(|g| g.client.contains(&instance.client.as_str()))
+        });
+        match (games, game) {
+            (Some(_), None) => {
+                crate::println_error!(
+                    "Unknown client '{}' for instance '{}'",
+                    instance.client,
+                    instance.name
+                );
+                std::process::exit(1);
+            }
+            (Some(_), Some(game)) if !updated.contains(&path) => {
+                update(
+                    game,
+                    &instance.client,
+                    &path,
+                    cfg.download_bonus_content,
+                    cfg.force_update,
+                    Some(ignore_required_files),
+                )
+                .await
+                .unwrap_or_else(|error| {
+                    update_failed_supervised(error, &path.join(format!("{}.exe", instance.client)))
+                });
+                updated.push(path.clone());
+            }
+            _ => {}
+        }
+
+        // resolved up front so the runner output of the instances isn't interleaved
+        let game_command = game_command(
+            &path.join(format!("{}.exe", instance.client)),
+            &fleet::instance_args(instance),
+            cfg,
+            game.map(|g| g.app_id),
+        );
+        let log_path = path
+            .join(&cfg.supervisor.log_dir)
+            .join(format!("{}.log", instance.name));
+        let hook_context = match game {
+            Some(game) => hooks::Context::new(game.engine, &instance.client, &path),
+            None => hooks::Context::for_client(&instance.client, &path),
+        };
+        commands.push((instance, game_command, log_path, hook_context));
+    }
+
+    let state_dir = fleet::state_dir(install_path);
+    let supervisors: Vec<_> = commands
+        .into_iter()
+        .map(|(instance, game_command, log_path, hook_context)| {
+            let name = instance.name.clone();
+            let port = instance.port;
+            let files = supervisor::InstanceFiles::new(&state_dir, &name);
+            let supervisor_cfg = cfg.supervisor.clone();
+            std::thread::spawn(move || {
+                if let Err(error) = supervisor::supervise(
+                    &name,
+                    &game_command,
+                    &supervisor_cfg,
+                    log_path,
+                    Some(&files),
+                    Some(SocketAddr::from(([127, 0, 0, 1], port))),
+                    &hook_context,
+                ) {
+                    crate::println_error!("{error}");
+                }
+            })
+        })
+        .collect();
+
+    match (daemon, games) {
+        (true, Some(games)) => {
+            updater::run(games, &cfg.instances, install_path, cfg, &supervisors).await
+        }
+        (true, None) => crate::println_info!("Offline mode, not checking for updates"),
+        _ => {}
+    }
+    for supervisor in supervisors {
+        let _ = supervisor.join();
+    }
+    webhooks::flush();
+}
+
+/// Serve `/metrics` if an address is configured, the launcher keeps running without it
+fn serve_metrics(cfg: &Config) {
+    if cfg.supervisor.metrics_address.is_empty() {
+        return;
+    }
+    match metrics::serve(&cfg.supervisor.metrics_address) {
+        Ok(address) => crate::println_info!("Serving metrics on http://{address}/metrics"),
+        Err(error) => crate::println_error!("{error}"),
+    }
+}
+
+/// Run a dedicated server under the supervisor instead of launching it once
+fn supervise(file_path: &Path, args: &[String], cfg: &Config, app_id: Option<u32>, client: &str) {
+    serve_metrics(cfg);
+    let game = game_command(file_path, args, cfg, app_id);
+    let log_path = file_path
+        .parent()
+        .unwrap()
+        .join(&cfg.supervisor.log_dir)
+        .join(format!("{client}.log"));
+    // without a net_port in the args the server's port is unknown, so it isn't health checked
+    let health_check =
+        supervisor::net_port(args).map(|port| SocketAddr::from(([127, 0, 0, 1], port)));
+    let hook_context =
+        hooks::Context::for_client(client, file_path.parent().unwrap_or(Path::new(".")));
+
+    let result = supervisor::supervise(
+        client,
+        &game,
+        &cfg.supervisor,
+        log_path,
+        None,
+        health_check,
+        &hook_context,
+    );
+    webhooks::flush();
+    if let Err(error) = result {
+        crate::println_error!("{error}");
+        std::process::exit(1);
+    }
+}
+
 #[cfg(windows)]
 fn setup_env() {
     colored::control::set_virtual_terminal(true).unwrap_or_else(|error| {
@@ -569,6 +1030,61 @@ fn arg_remove_value(args: &mut Vec<String>, arg: &str) {
     };
 }
 
+/// Remove `arg` and everything after it from the launcher args, returning what followed it
+fn arg_split_off(args: &mut Vec<String>, arg: &str) -> Vec<String> {
+    if let Some(e) = args.iter().position(|r| r == arg) {
+        let rest = args.split_off(e + 1);
+        args.pop();
+        rest
+    } else {
+        Vec::new()
+    }
+}
+
+/// Commands managing servers of another launcher process, e.g. a fleet daemon still writing the log
+fn keeps_launcher_log(args: &[String]) -> bool {
+    let args: Vec<&str> = args
+        .iter()
+        .skip(1)
+        .take_while(|arg| *arg != "--")
+        .map(String::as_str)
+        .collect();
+    args.iter().any(|arg| matches!(*arg, "rcon" | "systemd"))
+        || args
+            .windows(2)
+            .any(|pair| pair[0] == "fleet" && matches!(pair[1], "status" | "stop"))
+}
+
+/// Filter and sort order of the server browser, removed from the launcher args
+fn server_filter(args: &mut Vec<String>) -> (servers::Filter, servers::SortKey) {
+    let filter = servers::Filter {
+        text: arg_value(args, "--filter").unwrap_or_default(),
+        not_empty: arg_bool(args, "--not-empty"),
+        not_full: arg_bool(args, "--not-full"),
+    };
+    arg_remove_value(args, "--filter");
+    arg_remove(args, "--not-empty");
+    arg_remove(args, "--not-full");
+
+    let sort = match arg_value(args, "--sort").map(|key| key.parse()) {
+        Some(Ok(sort)) => sort,
+        Some(Err(error)) => {
+            crate::println_error!("{error}");
+            std::process::exit(1);
+        }
+        None => servers::SortKey::Players,
+    };
+    arg_remove_value(args, "--sort");
+    (filter, sort)
+}
+
+/// Combine the configured game args with the ones passed after `--`
+fn game_args(args: &str, passthrough: &[String]) -> Vec<String> {
+    let mut game_args = misc::split_args(args);
+    game_args.extend_from_slice(passthrough);
+    game_args
+}
+
 fn show_iw4x_info() {
     println!(
         "{}",
@@ -581,14 +1097,11 @@ fn show_iw4x_info() {
 
 #[tokio::main]
 async fn main() {
-    #[cfg(windows)]
-    let log_file = env::current_exe()
-        .unwrap_or(PathBuf::from("alterware-launcher"))
-        .with_extension("log");
-    #[cfg(unix)]
-    let log_file = PathBuf::from("/var/log/alterware-launcher.log");
-
-    if log_file.exists() && fs::remove_file(&log_file).is_err() {
+    let log_file = logs::launcher_log();
+    if !keeps_launcher_log(&env::args().collect::<Vec<_>>())
+        && log_file.exists()
+        && fs::remove_file(&log_file).is_err()
+    {
         println!("Couldn't clear log file, make sure target directory is writable.");
     }
     let logger_config = LogConfigBuilder::builder()
@@ -604,6 +1117,20 @@ async fn main() {
     setup_env();
 
     let mut args: Vec<String> = env::args().collect();
+    #[cfg(unix)]
+    let compat_tool = arg_bool(&args, "--compat-tool");
+    #[cfg(not(unix))]
+    let compat_tool = false;
+
+    let steam_command = if arg_bool(&args, "--steam-wrap") {
+        Some(arg_split_off(&mut args, "--steam-wrap"))
+    } else if compat_tool {
+        // <verb> <game exe> <args>, the compatibility tool is resolved after the config is loaded
+        Some(arg_split_off(&mut args, "--compat-tool"))
+    } else {
+        None
+    };
+    let passthrough_args = arg_split_off(&mut args, "--");
 
     if args.iter().any(|arg| arg == "iw4x") {
         show_iw4x_info();
@@ -626,6 +1153,26 @@ async fn main() {
         println!("    --redist: (Re-)Install redistributables");
         println!("    --prerelease: Update to prerelease version of clients and launcher");
         println!("    --offline: Run in offline mode");
+        println!("    --install-compat-tool: Register the launcher as a Steam compatibility tool (Linux)");
+        println!("    --steam-shortcuts [--grid <dir>]: Add the clients to Steam as non-Steam games (Linux)");
+        println!("    --remove-shortcuts: Remove the application menu and desktop entries (Linux)");
+        println!(
+            "    --runner <runner>: Launch using auto, umu, wine, proton, native or custom (Linux)"
+        );
+        println!("    --connect <host:port>: Connect to a server after launching");
+        println!("    fleet [start|status|stop] [name]: Run the dedicated server instances from the config");
+        println!("    fleet start --daemon: Also check for client updates and restart the instances onto them when empty");
+        println!("    systemd <client|fleet> [--profile <server cfg>] [--system] [--print]: Write a systemd unit running a dedicated server (Linux)");
+        println!("    rcon <host:port> [command]: Run a command on a dedicated server, opens a console without a command");
+        println!("    servers favorites [--tag <tag>]: List favorite and recent servers to join");
+        println!(
+            "    servers favorite|unfavorite <host:port> [tags]: Add or remove a favorite server"
+        );
+        println!("    servers [client] [--filter <text>] [--sort players|ping|name|map] [--not-empty] [--not-full]: Browse and join IW6/S1 servers");
+        println!("    -- <args>: Pass all following arguments to the game as-is");
+        println!("    --supervise: Restart a dedicated server when it crashes and write its output to log files");
+        println!("    --steam-launch: Launch through Steam, requires --steam-wrap in the Steam launch options");
+        println!("    --steam-wrap %command%: Use as Steam launch options to update and launch through Steam");
         println!("\nExample:\n    alterware-launcher.exe iw6 --pass \"-headless\"");
         return;
     }
@@ -650,6 +1197,22 @@ async fn main() {
         return;
     }
 
+    let connect_link = match args.iter().position(|arg| misc::is_connect_uri(arg)) {
+        Some(index) => {
+            let uri = args.remove(index);
+            info!("Opening link {uri}");
+            match misc::parse_connect_uri(&uri) {
+                Ok(link) => Some(link),
+                Err(error) => {
+                    crate::println_error!("{error}");
+                    misc::stdin();
+                    std::process::exit(1);
+                }
+            }
+        }
+        None => None,
+    };
+
     let install_path: PathBuf;
     if let Some(path) = arg_value(&args, "--path") {
         install_path = PathBuf::from(path);
@@ -657,17 +1220,223 @@ async fn main() {
     } else if let Some(path) = arg_value(&args, "-p") {
         install_path = PathBuf::from(path);
         arg_remove_value(&mut args, "-p");
+    } else if let Some(exe) = steam_command
+        .as_ref()
+        .and_then(|command| Some(&command[steam::wrapped_exe(command)?]))
+    {
+        install_path = Path::new(exe)
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| env::current_dir().unwrap());
+        info!(
+            "Steam wrap mode, game directory: {}",
+            install_path.display()
+        );
+    } else if let Some(data) = connect_link
+        .as_ref()
+        .and_then(|_| cache::get_stored_data())
+        .filter(|data| !data.game_path.is_empty())
+    {
+        install_path = PathBuf::from(data.game_path);
+        info!("Opening link for install {}", install_path.display());
     } else {
         install_path = env::current_dir().unwrap();
     }
 
     let mut cfg = config::load(install_path.join("alterware-launcher.json"));
+    webhooks::configure(cfg.webhooks.clone());
+    hooks::configure(cfg.hooks.clone());
+
+    #[cfg(unix)]
+    let steam_command = if compat_tool {
+        steam_command.map(|command| steam::compat_tool_command(command, &cfg))
+    } else {
+        steam_command
+    };
+
+    // a launch staged by --steam-launch decides client and args when Steam runs the wrapper
+    let mut staged_launch = false;
+    let passthrough_args = match steam_command
+        .as_ref()
+        .and_then(|_| cache::take_staged_launch(&install_path))
+    {
+        Some(staged) => {
+            info!("Using staged launch for {}", staged.client);
+            if args.len() <= 1 {
+                args.insert(1, staged.client);
+            }
+            staged_launch = true;
+            staged.args
+        }
+        None => passthrough_args,
+    };
+
+    let run_fleet_instances = args.get(1).is_some_and(|arg| arg == "fleet");
+    let fleet_daemon = run_fleet_instances && arg_bool(&args, "--daemon");
+    if run_fleet_instances {
+        arg_remove(&mut args, "--daemon");
+        let name = args.get(3).map(String::as_str);
+        match args.get(2).map(String::as_str) {
+            Some(command @ ("status" | "stop")) => {
+                match fleet::select(&cfg.instances, name) {
+                    Ok(instances) if command == "status" => {
+                        fleet::print_status(&instances, &install_path)
+                    }
+                    Ok(instances) => fleet::stop(&instances, &install_path),
+                    Err(error) => {
+                        crate::println_error!("{error}");
+                        std::process::exit(1);
+                    }
+                }
+                std::process::exit(0);
+            }
+            None | Some("start") => {}
+            Some(other) => {
+                crate::println_error!(
+                    "Unknown fleet command '{other}', expected start, status or stop"
+                );
+                std::process::exit(1);
+            }
+        }
+        args.remove(1);
+        if args.get(1).is_some_and(|arg| arg == "start") {
+            args.remove(1);
+        }
+    }
+
+    #[cfg(unix)]
+    if args.get(1).is_some_and(|arg| arg == "systemd") {
+        let system = arg_bool(&args, "--system");
+        arg_remove(&mut args, "--system");
+        let print = arg_bool(&args, "--print");
+        arg_remove(&mut args, "--print");
+        let profile = arg_value(&args, "--profile");
+        arg_remove_value(&mut args, "--profile");
+        let Some(target) = args.get(2) else {
+            crate::println_error!(
+                "Usage: alterware-launcher systemd <client|fleet> [--profile <server cfg>] [--system] [--print] [-- <args>]"
+            );
+            std::process::exit(1);
+        };
+        if let Err(error) = systemd::install(
+            target,
+            profile.as_deref(),
+            &passthrough_args,
+            &install_path,
+            system,
+            print,
+        ) {
+            crate::println_error!("{error}");
+            std::process::exit(1);
+        }
+        std::process::exit(0);
+    }
+
+    if args.get(1).is_some_and(|arg| arg == "rcon") {
+        let Some(address) = args.get(2) else {
+            crate::println_error!("Usage: alterware-launcher rcon <host:port> [command]");
+            std::process::exit(1);
+        };
+        if let Err(error) = rcon::console(address, &args[3..], &cfg) {
+            crate::println_error!("{error}");
+            std::process::exit(1);
+        }
+        std::process::exit(0);
+    }
+
+    // favorites and recent servers are kept next to the stored game data
+    let state_dir = env::current_dir().unwrap();
+    let mut connect_hostname = None;
+    let connect_address = if args.get(1).is_some_and(|arg| arg == "servers") {
+        args.remove(1);
+        let (address, hostname) = match args.get(1).map(String::as_str) {
+            Some("favorites") => {
+                args.remove(1);
+                let tag = arg_value(&args, "--tag");
+                arg_remove_value(&mut args, "--tag");
+                servers::browse_saved(&state_dir, tag.as_deref())
+            }
+            Some(command @ ("favorite" | "unfavorite")) => {
+                let favorite = command == "favorite";
+                let Some(address) = args.get(2) else {
+                    crate::println_error!(
+                        "Usage: alterware-launcher servers {command} <host:port>"
+                    );
+                    std::process::exit(1);
+                };
+                if let Err(error) = misc::validate_address(address) {
+                    crate::println_error!("{error}");
+                    std::process::exit(1);
+                }
+                cache::set_favorite(&state_dir, address, favorite, &args[3..]);
+                if favorite {
+                    crate::println_info!("Added {address} to the favorites");
+                } else {
+                    crate::println_info!("Removed {address} from the favorites");
+                }
+                std::process::exit(0);
+            }
+            _ => {
+                let (filter, sort) = server_filter(&mut args);
+                let client = args
+                    .get(1)
+                    .filter(|arg| !arg.starts_with('-'))
+                    .unwrap_or(&cfg.engine);
+                let master = if cfg.master_server.is_empty() {
+                    DEFAULT_SERVER_MASTER
+                } else {
+                    cfg.master_server.as_str()
+                };
+                servers::browse(client, master, &filter, sort)
+            }
+        };
+        connect_hostname = hostname;
+        Some(address)
+    } else {
+        match arg_value(&args, "--connect") {
+            Some(address) => {
+                arg_remove_value(&mut args, "--connect");
+                if let Err(error) = misc::validate_address(&address) {
+                    crate::println_error!("{error}");
+                    misc::stdin();
+                    std::process::exit(1);
+                }
+                Some(address)
+            }
+            None => connect_link.as_ref().map(|(address, _)| address.clone()),
+        }
+    };
+    let link_client = connect_link.and_then(|(_, client)| client);
+
+    let passthrough_args = match connect_address {
+        Some(address) => {
+            info!("Connecting to {address}");
+            cache::record_server(&state_dir, &address, connect_hostname.as_deref());
+            [passthrough_args, vec!["+connect".to_string(), address]].concat()
+        }
+        None => passthrough_args,
+    };
+
+    if arg_bool(&args, "--supervise") {
+        cfg.supervise = true;
+        arg_remove(&mut args, "--supervise");
+    }
+
+    if arg_bool(&args, "--steam-launch") {
+        cfg.steam_launch = true;
+        arg_remove(&mut args, "--steam-launch");
+    }
 
     if let Some(cdn_url) = arg_value(&args, "--cdn-url") {
         cfg.cdn_url = cdn_url;
         arg_remove_value(&mut args, "--cdn-url");
     }
 
+    if let Some(runner) = arg_value(&args, "--runner") {
+        cfg.runner = runner;
+        arg_remove_value(&mut args, "--runner");
+    }
+
     if arg_bool(&args, "--offline") {
         cfg.offline = true;
         arg_remove(&mut args, "--offline");
@@ -680,6 +1449,11 @@ async fn main() {
         None
     };
 
+    if cfg.offline && run_fleet_instances {
+        run_fleet(None, &install_path, &cfg, false, fleet_daemon).await;
+        return;
+    }
+
     if cfg.offline {
         // Check if this is a first-time run (no stored data)
         let stored_data = cache::get_stored_data();
@@ -712,6 +1486,8 @@ async fn main() {
         // Get client from args, config, or prompt user
         let client = if args.len() > 1 {
             args[1].clone()
+        } else if let Some(client) = link_client.clone() {
+            client
         } else if let Some(engine) = stored_data
             .as_ref()
             .and_then(|d| d.clients.get(&cfg.engine))
@@ -722,7 +1498,7 @@ async fn main() {
                     println!("{i}: {c}");
                 }
                 info!("Multiple clients available, prompting user for selection");
-                engine[misc::stdin().parse::<usize>().unwrap()].clone()
+                engine[misc::stdin_number(|&i: &usize| i < engine.len())].clone()
             } else if !engine.is_empty() {
                 info!("Using single available client: {}", engine[0]);
                 engine[0].clone()
@@ -745,7 +1521,41 @@ async fn main() {
 
         info!("Launching game in offline mode with client: {client}");
         // Launch game without updates
-        launch(&install_path.join(format!("{client}.exe")), &cfg.args);
+        if let Some(command) = &steam_command {
+            steam::launch_wrapped(
+                command,
+                &install_path.join(format!("{client}.exe")),
+                &game_args(&cfg.args, &passthrough_args),
+            );
+        }
+        let app_id = stored_data
+            .as_ref()
+            .and_then(|d| d.app_ids.get(&cfg.engine).copied());
+        if let (true, Some(app_id)) = (cfg.steam_launch, app_id) {
+            steam::launch(
+                &install_path,
+                &client,
+                &game_args(&cfg.args, &passthrough_args),
+                app_id,
+            );
+            return;
+        }
+        if cfg.supervise {
+            supervise(
+                &install_path.join(format!("{client}.exe")),
+                &game_args(&cfg.args, &passthrough_args),
+                &cfg,
+                app_id,
+                &client,
+            );
+            return;
+        }
+        launch(
+            &install_path.join(format!("{client}.exe")),
+            &game_args(&cfg.args, &passthrough_args),
+            &cfg,
+            app_id,
+        );
         return;
     }
 
@@ -759,6 +1569,11 @@ async fn main() {
         arg_remove(&mut args, "--prerelease");
     }
 
+    // restarting after a self-update would detach the game from Steam
+    if steam_command.is_some() {
+        cfg.skip_self_update = true;
+    }
+
     if !arg_bool(&args, "--skip-launcher-update") && !cfg.skip_self_update {
         self_update::run(cfg.update_only, Some(cfg.prerelease)).await;
     } else {
@@ -792,18 +1607,53 @@ async fn main() {
         arg_remove(&mut args, "--ignore-required-files");
     }
 
+    #[cfg(unix)]
+    let steam_shortcuts = arg_bool(&args, "--steam-shortcuts");
+    #[cfg(unix)]
+    arg_remove(&mut args, "--steam-shortcuts");
+    #[cfg(unix)]
+    let grid_dir = arg_value(&args, "--grid").map(PathBuf::from);
+    #[cfg(unix)]
+    if grid_dir.is_some() {
+        arg_remove_value(&mut args, "--grid");
+    }
+
     if let Some(pass) = arg_value(&args, "--pass") {
         cfg.args = pass;
         arg_remove_value(&mut args, "--pass");
     } else if cfg.args.is_empty() {
         cfg.args = String::default();
     }
+    // the staged args already include the configured ones
+    if staged_launch {
+        cfg.args.clear();
+    }
 
     if arg_bool(&args, "--skip-redist") {
         cfg.skip_redist = true;
         arg_remove(&mut args, "--skip-redist");
     }
 
+    #[cfg(unix)]
+    if arg_bool(&args, "--remove-shortcuts") {
+        for entry in desktop::remove_entries() {
+            println!("{}{}", misc::prefix("removed"), entry.cute_path());
+        }
+        std::process::exit(0);
+    }
+
+    #[cfg(unix)]
+    if arg_bool(&args, "--install-compat-tool") {
+        match steam::install_compat_tool(&env::current_exe().unwrap()) {
+            Ok(dir) => {
+                crate::println_info!("Installed compatibility tool to {}", dir.cute_path());
+                println!("Restart Steam, then select \"AlterWare\" under Properties > Compatibility of a supported game.");
+            }
+            Err(error) => crate::println_error!("Failed to install compatibility tool: {error}"),
+        }
+        std::process::exit(0);
+    }
+
     #[cfg(windows)]
     if arg_bool(&args, "--redist") {
         arg_remove(&mut args, "--redist");
@@ -825,15 +1675,29 @@ async fn main() {
         std::process::exit(1);
     });
 
+    if run_fleet_instances {
+        run_fleet(
+            Some(&games),
+            &install_path,
+            &cfg,
+            ignore_required_files,
+            fleet_daemon,
+        )
+        .await;
+        return;
+    }
+
     let mut game: String = String::new();
     if args.len() > 1 {
         game = String::from(&args[1]);
+    } else if let Some(client) = link_client {
+        game = client;
     } else {
         'main: for g in games.iter() {
             for r in g.references.iter() {
                 if install_path.join(r).exists() {
                     if g.client.len() > 1 {
-                        if cfg.update_only {
+                        if cfg.update_only || steam_command.is_some() {
                             game = String::from(g.client[0]);
                             break 'main;
                         }
@@ -847,7 +1711,9 @@ async fn main() {
                         for (i, c) in g.client.iter().enumerate() {
                             println!("{i}: {c}");
                         }
-                        game = String::from(g.client[misc::stdin().parse::<usize>().unwrap()]);
+                        game = String::from(
+                            g.client[misc::stdin_number(|&i: &usize| i < g.client.len())],
+                        );
                         break 'main;
                     }
                     game = String::from(g.client[0]);
@@ -864,6 +1730,12 @@ async fn main() {
     for g in games.iter() {
         for c in g.client.iter() {
             if c == &game {
+                #[cfg(unix)]
+                if steam_shortcuts {
+                    setup_steam_shortcuts(&install_path, g, grid_dir.as_deref());
+                    return;
+                }
+
                 if cfg.engine.is_empty() {
                     cfg.engine = String::from(g.engine);
                     config::save_value_s(
@@ -896,14 +1768,51 @@ async fn main() {
 
                 update(
                     g,
+                    c,
                     install_path.as_path(),
                     cfg.download_bonus_content,
                     cfg.force_update,
                     Some(ignore_required_files),
                 )
-                .await;
+                .await
+                .unwrap_or_else(|error| {
+                    if cfg.supervise && !cfg.update_only && steam_command.is_none() {
+                        update_failed_supervised(error, &install_path.join(format!("{c}.exe")))
+                    } else {
+                        update_failed(error)
+                    }
+                });
+                if let Some(command) = &steam_command {
+                    steam::launch_wrapped(
+                        command,
+                        &install_path.join(format!("{c}.exe")),
+                        &game_args(&cfg.args, &passthrough_args),
+                    );
+                }
                 if !cfg.update_only {
-                    launch(&install_path.join(format!("{c}.exe")), &cfg.args);
+                    if cfg.steam_launch {
+                        steam::launch(
+                            &install_path,
+                            c,
+                            &game_args(&cfg.args, &passthrough_args),
+                            g.app_id,
+                        );
+                    } else if cfg.supervise {
+                        supervise(
+                            &install_path.join(format!("{c}.exe")),
+                            &game_args(&cfg.args, &passthrough_args),
+                            &cfg,
+                            Some(g.app_id),
+                            c,
+                        );
+                    } else {
+                        launch(
+                            &install_path.join(format!("{c}.exe")),
+                            &game_args(&cfg.args, &passthrough_args),
+                            &cfg,
+                            Some(g.app_id),
+                        );
+                    }
                 }
 
                 // Store game data for offline mode
@@ -915,6 +1824,7 @@ async fn main() {
                     g.engine.to_string(),
                     g.client.iter().map(|s| s.to_string()).collect(),
                 );
+                stored_data.app_ids.insert(g.engine.to_string(), g.app_id);
 
                 if let Err(e) = cache::store_game_data(&stored_data) {
                     println!(
@@ -929,8 +1839,7 @@ async fn main() {
         }
     }
 
-    #[cfg(windows)]
-    windows_launcher_install(&games).await;
+    launcher_install(&games).await;
 
     crate::println_error!("Game not found!");
     println!("Place the launcher in the game folder, if that doesn't work specify the client on the command line (ex. alterware-launcher.exe iw4-sp)");
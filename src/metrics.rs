@@ -0,0 +1,186 @@
+use crate::structs::InstanceStatus;
+use once_cell::sync::Lazy;
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    io::{BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+#[derive(Default)]
+struct Instance {
+    status: InstanceStatus,
+    restarts: u32,
+    /// Start of the current server process
+    started: Option<Instant>,
+    players: Option<(u32, u32)>,
+}
+
+#[derive(Default)]
+struct Metrics {
+    instances: BTreeMap<String, Instance>,
+    downloaded_bytes: u64,
+    /// Unix time and result of the last update
+    last_update: Option<(u64, bool)>,
+}
+
+static METRICS: Lazy<Mutex<Metrics>> = Lazy::new(|| Mutex::new(Metrics::default()));
+
+/// Record the state of a supervised instance, `status` and `restarts` as written to its state file
+pub fn instance_state(name: &str, status: InstanceStatus, restarts: u32) {
+    let mut metrics = METRICS.lock().unwrap();
+    let instance = metrics.instances.entry(name.to_string()).or_default();
+    if status == InstanceStatus::Running {
+        instance.started = Some(Instant::now());
+    } else {
+        instance.started = None;
+        instance.players = None;
+    }
+    instance.status = status;
+    instance.restarts = restarts;
+}
+
+/// Record the players seen by a health check
+pub fn instance_players(name: &str, players: u32, max_players: u32) {
+    let mut metrics = METRICS.lock().unwrap();
+    metrics
+        .instances
+        .entry(name.to_string())
+        .or_default()
+        .players = Some((players, max_players));
+}
+
+pub fn add_downloaded(bytes: u64) {
+    METRICS.lock().unwrap().downloaded_bytes += bytes;
+}
+
+pub fn update_finished(success: bool) {
+    METRICS.lock().unwrap().last_update = Some((crate::cache::unix_time(), success));
+}
+
+/// Metrics in the Prometheus text format
+pub fn render() -> String {
+    let metrics = METRICS.lock().unwrap();
+    let mut out = String::new();
+    let mut family = |name: &str, kind: &str, help: &str, samples: Vec<(String, String)>| {
+        let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} {kind}");
+        for (labels, value) in samples {
+            let _ = writeln!(out, "{name}{labels} {value}");
+        }
+    };
+    let per_instance = |value: &dyn Fn(&Instance) -> Option<String>| {
+        metrics
+            .instances
+            .iter()
+            .filter_map(|(name, instance)| {
+                value(instance).map(|value| (format!("{{instance=\"{}\"}}", escape(name)), value))
+            })
+            .collect::<Vec<_>>()
+    };
+
+    family(
+        "alterware_instance_up",
+        "gauge",
+        "Whether the server of the instance is running",
+        per_instance(&|i| Some(u8::from(i.status == InstanceStatus::Running).to_string())),
+    );
+    family(
+        "alterware_instance_restarts_total",
+        "counter",
+        "Restarts of the instance after crashes",
+        per_instance(&|i| Some(i.restarts.to_string())),
+    );
+    family(
+        "alterware_instance_uptime_seconds",
+        "gauge",
+        "Seconds since the server of the instance was started",
+        per_instance(&|i| {
+            Some(
+                i.started
+                    .map_or(0, |started| started.elapsed().as_secs())
+                    .to_string(),
+            )
+        }),
+    );
+    family(
+        "alterware_instance_players",
+        "gauge",
+        "Players connected to the instance, as reported by getstatus",
+        per_instance(&|i| i.players.map(|(players, _)| players.to_string())),
+    );
+    family(
+        "alterware_instance_max_players",
+        "gauge",
+        "Player slots of the instance, as reported by getstatus",
+        per_instance(&|i| i.players.map(|(_, max)| max.to_string())),
+    );
+    family(
+        "alterware_downloaded_bytes_total",
+        "counter",
+        "Bytes downloaded by updates",
+        vec![(String::new(), metrics.downloaded_bytes.to_string())],
+    );
+    if let Some((time, success)) = metrics.last_update {
+        family(
+            "alterware_last_update_timestamp_seconds",
+            "gauge",
+            "Unix time of the last update or update check",
+            vec![(String::new(), time.to_string())],
+        );
+        family(
+            "alterware_last_update_success",
+            "gauge",
+            "Whether the last update or update check succeeded",
+            vec![(String::new(), u8::from(success).to_string())],
+        );
+    }
+    out
+}
+
+/// Escape a label value
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn respond(mut stream: TcpStream) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+    let mut request = String::new();
+    BufReader::new(&stream).read_line(&mut request)?;
+
+    let path = request.split_whitespace().nth(1).unwrap_or_default();
+    let (status, body) = match path.split('?').next() {
+        Some("/metrics") => ("200 OK", render()),
+        _ => (
+            "404 Not Found",
+            "Not found, metrics are at /metrics\n".to_string(),
+        ),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\n\
+         Content-Type: text/plain; version=0.0.4; charset=utf-8\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+/// Serve `/metrics` on `address` in the background, returning the bound address
+pub fn serve(address: &str) -> Result<SocketAddr, String> {
+    let listener = TcpListener::bind(address)
+        .map_err(|e| format!("Failed to listen for metrics on {address}: {e}"))?;
+    let address = listener.local_addr().map_err(|e| e.to_string())?;
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if let Err(error) = respond(stream) {
+                debug!("Failed to answer metrics request: {error}");
+            }
+        }
+    });
+    Ok(address)
+}
@@ -13,6 +13,65 @@ pub fn stdin() -> String {
     input.trim().to_string()
 }
 
+/// Download attempts made before giving up when there is no terminal to ask
+pub const DOWNLOAD_ATTEMPTS: u32 = 4;
+
+/// Delay before the next download after `attempt` failed ones, `None` once all attempts are used
+pub fn download_backoff(attempt: u32) -> Option<std::time::Duration> {
+    (attempt < DOWNLOAD_ATTEMPTS).then(|| std::time::Duration::from_secs(5 << (attempt - 1)))
+}
+
+/// Whether to retry a failed download.
+///
+/// The user is asked when stdin is a terminal. Otherwise, e.g. under systemd where stdin is
+/// closed, the download is retried with backoff until [`DOWNLOAD_ATTEMPTS`] is reached.
+pub async fn retry_download(question: &str, attempt: u32, interactive: bool) -> bool {
+    if interactive {
+        println!("{question} (Y/n)");
+        return !stdin().eq_ignore_ascii_case("n");
+    }
+    match download_backoff(attempt) {
+        Some(delay) => {
+            warn!(
+                "Retrying download in {}s, attempt {} of {DOWNLOAD_ATTEMPTS}",
+                delay.as_secs(),
+                attempt + 1
+            );
+            tokio::time::sleep(delay).await;
+            true
+        }
+        None => false,
+    }
+}
+
+/// Read numbers from stdin until `valid` accepts one, exits if stdin is closed
+pub fn stdin_number<T: std::str::FromStr>(valid: impl Fn(&T) -> bool) -> T {
+    loop {
+        match stdin_number_or_empty(&valid) {
+            Some(number) => return number,
+            None => crate::println_error!("Invalid selection '', try again:"),
+        }
+    }
+}
+
+/// Like [`stdin_number`], but an empty line returns `None`
+pub fn stdin_number_or_empty<T: std::str::FromStr>(valid: impl Fn(&T) -> bool) -> Option<T> {
+    loop {
+        let mut input = String::new();
+        if std::io::stdin().read_line(&mut input).unwrap_or(0) == 0 {
+            std::process::exit(1);
+        }
+        let input = input.trim();
+        if input.is_empty() {
+            return None;
+        }
+        match input.parse::<T>() {
+            Ok(number) if valid(&number) => return Some(number),
+            _ => crate::println_error!("Invalid selection '{input}', try again:"),
+        }
+    }
+}
+
 pub fn human_readable_bytes(bytes: u64) -> String {
     let mut bytes = bytes as f64;
     let mut i = 0;
@@ -24,6 +83,102 @@ pub fn human_readable_bytes(bytes: u64) -> String {
     format!("{bytes:.2}{}", UNITS[i])
 }
 
+/// Split a game argument string using POSIX shell quoting rules
+pub fn split_args(args: &str) -> Vec<String> {
+    shell_words::split(args).unwrap_or_else(|error| {
+        crate::println_error!(
+            "Failed to parse game arguments ({error}), falling back to splitting on whitespace"
+        );
+        args.split_whitespace().map(String::from).collect()
+    })
+}
+
+/// Join game arguments back into a string that can be pasted into a shell
+pub fn join_args(args: &[String]) -> String {
+    shell_words::join(args)
+}
+
+/// Placeholder for removed secrets in logs and crash reports
+pub const REDACTED: &str = "<redacted>";
+
+/// Whether an argument names a password dvar, e.g. `rcon_password`
+pub fn is_password_dvar(word: &str) -> bool {
+    word.trim_matches(|c| c == '"' || c == '\'')
+        .to_ascii_lowercase()
+        .ends_with("password")
+}
+
+/// Replace the values of password dvars in split launch arguments, e.g. `+set rcon_password hunter2`
+pub fn redact_words(words: &[String]) -> Vec<String> {
+    let mut words = words.to_vec();
+    for i in 1..words.len() {
+        if is_password_dvar(&words[i - 1]) {
+            words[i] = REDACTED.to_string();
+        }
+    }
+    words
+}
+
+/// Replace the values of password dvars in launch arguments, e.g. `+set rcon_password hunter2`
+pub fn redact_args(args: &str) -> String {
+    join_args(&redact_words(&split_args(args)))
+}
+
+/// Check a `host:port` server address, rejecting anything that could smuggle in extra game commands
+pub fn validate_address(address: &str) -> Result<(), String> {
+    let (host, port) = address
+        .rsplit_once(':')
+        .ok_or_else(|| format!("Invalid server address '{address}', expected host:port"))?;
+    if host.is_empty()
+        || !host
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || ".-:[]".contains(c))
+    {
+        return Err(format!("Invalid host in server address '{address}'"));
+    }
+    if port.parse::<u16>().map_or(true, |port| port == 0) {
+        return Err(format!("Invalid port in server address '{address}'"));
+    }
+    Ok(())
+}
+
+pub fn is_connect_uri(arg: &str) -> bool {
+    arg.get(..12)
+        .is_some_and(|scheme| scheme.eq_ignore_ascii_case("alterware://"))
+}
+
+/// Parse `alterware://connect/<host>:<port>?client=<client>` into the address and optional client
+pub fn parse_connect_uri(uri: &str) -> Result<(String, Option<String>), String> {
+    if !is_connect_uri(uri) {
+        return Err(format!("'{uri}' is not an alterware:// link"));
+    }
+    let (path, query) = uri[12..].split_once('?').unwrap_or((&uri[12..], ""));
+    let address = path
+        .strip_prefix("connect/")
+        .ok_or_else(|| {
+            format!("Unsupported link '{uri}', expected alterware://connect/<host>:<port>")
+        })?
+        .trim_end_matches('/');
+    validate_address(address)?;
+
+    let client = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == "client")
+        .map(|(_, client)| client.to_string())
+        .filter(|client| !client.is_empty());
+    if let Some(client) = &client {
+        if !client
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(format!("Invalid client '{client}' in link"));
+        }
+    }
+
+    Ok((address.to_string(), client))
+}
+
 pub fn pb_style_download(pb: &ProgressBar, state: bool) {
     let style = if state {
         ProgressStyle::with_template(
@@ -48,6 +203,45 @@ pub fn is_program_in_path(program: &str) -> bool {
         .unwrap_or(false)
 }
 
+/// Expand a leading `~` to the user's home directory
+#[cfg(unix)]
+pub fn expand_home(path: &str) -> std::path::PathBuf {
+    match (path.strip_prefix('~'), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+            std::path::PathBuf::from(home).join(rest.trim_start_matches('/'))
+        }
+        _ => std::path::PathBuf::from(path),
+    }
+}
+
+/// Per-user data directory, `$XDG_DATA_HOME` or `~/.local/share`
+#[cfg(unix)]
+pub fn data_home() -> Option<std::path::PathBuf> {
+    std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(std::path::PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| std::path::Path::new(&home).join(".local/share"))
+        })
+}
+
+/// Per-user config directory, `$XDG_CONFIG_HOME` or `~/.config`
+#[cfg(unix)]
+pub fn config_home() -> Option<std::path::PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(std::path::PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| std::path::Path::new(&home).join(".config"))
+        })
+}
+
+/// Per-user data directory of the launcher, `$XDG_DATA_HOME/alterware-launcher`
+#[cfg(unix)]
+pub fn data_dir() -> Option<std::path::PathBuf> {
+    data_home().map(|dir| dir.join("alterware-launcher"))
+}
+
 #[cfg(windows)]
 pub fn is_program_in_path(program: &str) -> bool {
     std::env::var_os("PATH")
@@ -0,0 +1,132 @@
+use crate::servers::{self, OOB};
+use crate::structs::Config;
+use std::{
+    net::{SocketAddr, UdpSocket},
+    time::{Duration, Instant},
+};
+
+/// Environment variable taking precedence over `rcon_password` in the config
+pub const PASSWORD_ENV: &str = "ALTERWARE_RCON_PASSWORD";
+
+/// Password from the environment or the config
+pub fn password(cfg: &Config) -> String {
+    std::env::var(PASSWORD_ENV)
+        .ok()
+        .filter(|password| !password.is_empty())
+        .unwrap_or_else(|| cfg.rcon_password.clone())
+}
+
+/// Time to wait for more packets once a response started arriving, long output is split up
+const QUIET_PERIOD: Duration = Duration::from_millis(250);
+
+pub struct Rcon {
+    socket: UdpSocket,
+    address: SocketAddr,
+    password: String,
+    timeout: Duration,
+}
+
+/// Text of a `print` response
+pub fn parse_print_response(data: &[u8]) -> Option<String> {
+    servers::oob_payload(data, "print").map(|payload| String::from_utf8_lossy(payload).into_owned())
+}
+
+impl Rcon {
+    pub fn new(address: &str, password: &str, timeout: Duration) -> Result<Self, String> {
+        if password.is_empty() {
+            return Err(format!(
+                "No rcon password set, set rcon_password in the config or {PASSWORD_ENV}"
+            ));
+        }
+        let address = servers::resolve(address)?;
+        let socket =
+            UdpSocket::bind("0.0.0.0:0").map_err(|e| format!("Failed to bind socket: {e}"))?;
+        Ok(Self {
+            socket,
+            address,
+            password: password.to_string(),
+            timeout,
+        })
+    }
+
+    /// Run a command on the server and return its output
+    pub fn send(&self, command: &str) -> Result<String, String> {
+        let packet = [
+            OOB,
+            format!("rcon {} {}", self.password, command.trim()).as_bytes(),
+        ]
+        .concat();
+        self.socket
+            .send_to(&packet, self.address)
+            .map_err(|e| format!("Failed to send to {}: {e}", self.address))?;
+
+        let mut deadline = Instant::now() + self.timeout;
+        let mut output: Option<String> = None;
+        let mut buffer = [0u8; 65536];
+        while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+            self.socket
+                .set_read_timeout(Some(remaining.max(Duration::from_millis(1))))
+                .ok();
+            let Ok((len, from)) = self.socket.recv_from(&mut buffer) else {
+                break;
+            };
+            if from != self.address {
+                continue;
+            }
+            if let Some(text) = parse_print_response(&buffer[..len]) {
+                output.get_or_insert_with(String::new).push_str(&text);
+                deadline = Instant::now() + QUIET_PERIOD;
+            }
+        }
+
+        output.ok_or_else(|| {
+            format!(
+                "No response from {}, check the address and that the server is running",
+                self.address
+            )
+        })
+    }
+}
+
+/// Send `command` to a dedicated server, without a command an interactive console is started
+pub fn console(address: &str, command: &[String], cfg: &Config) -> Result<(), String> {
+    let rcon = Rcon::new(address, &password(cfg), Duration::from_secs(2))?;
+
+    let print_output = |output: String| {
+        let output = servers::strip_colors(&output);
+        if output.ends_with('\n') {
+            print!("{output}");
+        } else {
+            println!("{output}");
+        }
+    };
+
+    if !command.is_empty() {
+        print_output(rcon.send(&command.join(" "))?);
+        return Ok(());
+    }
+
+    println!("Connected to {address}, type quit or press Ctrl+D to exit");
+    let mut line = String::new();
+    loop {
+        print!("rcon> ");
+        std::io::Write::flush(&mut std::io::stdout()).ok();
+        line.clear();
+        if std::io::stdin()
+            .read_line(&mut line)
+            .map_err(|e| e.to_string())?
+            == 0
+        {
+            println!();
+            return Ok(());
+        }
+        match line.trim() {
+            "" => continue,
+            "quit" | "exit" => return Ok(()),
+            command => match rcon.send(command) {
+                Ok(output) => print_output(output),
+                Err(error) => crate::println_error!("{error}"),
+            },
+        }
+    }
+}
@@ -0,0 +1,302 @@
+use crate::misc;
+use crate::steam;
+use crate::structs::Config;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_LAUNCH_COMMAND: &str = "{runner} {exe} {args}";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Runner {
+    Umu,
+    Wine,
+    Proton(PathBuf),
+    Native,
+    Custom(Vec<String>),
+}
+
+impl Runner {
+    /// Resolve the runner set in the config, auto-detecting it if unset
+    pub fn from_config(cfg: &Config) -> Result<Self, String> {
+        match cfg.runner.trim().to_ascii_lowercase().as_str() {
+            "" | "auto" => Ok(Self::detect()),
+            "umu" => Ok(Self::Umu),
+            "wine" => Ok(Self::Wine),
+            "proton" => steam::find_proton(&cfg.proton_version)
+                .map(Self::Proton)
+                .ok_or_else(|| {
+                    if cfg.proton_version.trim().is_empty() {
+                        "runner is set to proton but no Proton install was found".to_string()
+                    } else {
+                        format!("Proton version '{}' not found", cfg.proton_version)
+                    }
+                }),
+            "native" => Ok(Self::Native),
+            "custom" => {
+                let command = misc::split_args(&cfg.runner_command);
+                if command.is_empty() {
+                    Err("runner is set to custom but runner_command is empty".to_string())
+                } else {
+                    Ok(Self::Custom(command))
+                }
+            }
+            other => Err(format!(
+                "Unknown runner '{other}', expected one of auto, umu, wine, proton, native, custom"
+            )),
+        }
+    }
+
+    /// Pick umu-run if available, then wine, then native execution
+    pub fn detect() -> Self {
+        if misc::is_program_in_path("umu-run") {
+            Self::Umu
+        } else if misc::is_program_in_path("wine") {
+            Self::Wine
+        } else {
+            Self::Native
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Umu => "umu-run",
+            Self::Wine => "wine",
+            Self::Proton(_) => "proton",
+            Self::Native => "native",
+            Self::Custom(command) => &command[0],
+        }
+    }
+
+    /// Version reported by the runner, `None` for native execution or if it can't be determined
+    pub fn version(&self) -> Option<String> {
+        let output = |program: &str| {
+            std::process::Command::new(program)
+                .arg("--version")
+                .output()
+                .ok()
+                .filter(|output| output.status.success())
+                .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+                .filter(|version| !version.is_empty())
+        };
+        match self {
+            Self::Umu => output("umu-run"),
+            Self::Wine => output("wine"),
+            // Proton builds ship a file with the build time and name, e.g. "1718383502 GE-Proton9-7"
+            Self::Proton(dir) => std::fs::read_to_string(dir.join("version"))
+                .ok()
+                .map(|version| version.trim().to_string()),
+            Self::Native => None,
+            Self::Custom(command) => output(&command[0]),
+        }
+    }
+
+    /// Command line that is substituted for `{runner}` in the launch template
+    pub fn command(&self) -> Vec<String> {
+        match self {
+            Self::Umu => vec!["umu-run".to_string()],
+            Self::Wine => vec!["wine".to_string()],
+            Self::Proton(dir) => vec![
+                dir.join("proton").to_string_lossy().into_owned(),
+                "run".to_string(),
+            ],
+            Self::Native => Vec::new(),
+            Self::Custom(command) => command.clone(),
+        }
+    }
+}
+
+/// Expand a launch command template into the final command line.
+///
+/// `{runner}` and `{args}` expand to zero or more words when they stand alone,
+/// `{exe}` expands to the client executable. Placeholders embedded in a larger
+/// word are substituted as plain text.
+pub fn expand_template(
+    template: &str,
+    runner: &[String],
+    exe: &Path,
+    args: &[String],
+) -> Vec<String> {
+    let exe = exe.to_string_lossy();
+    let mut command = Vec::new();
+
+    for word in misc::split_args(template) {
+        match word.as_str() {
+            "{runner}" => command.extend_from_slice(runner),
+            "{args}" => command.extend_from_slice(args),
+            "{exe}" => command.push(exe.to_string()),
+            _ => command.push(
+                word.replace("{runner}", &misc::join_args(runner))
+                    .replace("{exe}", &exe)
+                    .replace("{args}", &misc::join_args(args)),
+            ),
+        }
+    }
+
+    command
+}
+
+/// Build the command line for launching `exe` according to the config
+pub fn launch_command(cfg: &Config, runner: &Runner, exe: &Path, args: &[String]) -> Vec<String> {
+    let template = if cfg.launch_command.trim().is_empty() {
+        DEFAULT_LAUNCH_COMMAND
+    } else {
+        cfg.launch_command.as_str()
+    };
+    expand_template(template, &runner.command(), exe, args)
+}
+
+/// Resolve the wine prefix for this install.
+///
+/// An explicit `wine_prefix` wins, otherwise `manage_prefix` gives every engine
+/// its own prefix in the launcher data directory.
+pub fn wine_prefix(cfg: &Config) -> Option<PathBuf> {
+    if !cfg.wine_prefix.trim().is_empty() {
+        return Some(misc::expand_home(cfg.wine_prefix.trim()));
+    }
+
+    if cfg.manage_prefix {
+        let engine = if cfg.engine.is_empty() {
+            "default"
+        } else {
+            cfg.engine.as_str()
+        };
+        return misc::data_dir().map(|dir| dir.join("prefixes").join(engine));
+    }
+
+    None
+}
+
+/// Compat data directory used by Proton for this game.
+///
+/// Reuses the prefix Steam created for the game, falling back to a per-engine
+/// directory in the launcher data directory if the app id is unknown.
+pub fn proton_compat_data(cfg: &Config, exe: &Path, app_id: Option<u32>) -> Option<PathBuf> {
+    match app_id {
+        Some(app_id) => steam::compat_data_path(exe, app_id),
+        None => {
+            let engine = if cfg.engine.is_empty() {
+                "default"
+            } else {
+                cfg.engine.as_str()
+            };
+            misc::data_dir().map(|dir| dir.join("compatdata").join(engine))
+        }
+    }
+}
+
+/// Environment variables for the game process, user-set variables take precedence
+pub fn launch_env(
+    cfg: &Config,
+    runner: &Runner,
+    exe: &Path,
+    app_id: Option<u32>,
+) -> Vec<(String, String)> {
+    let mut env = Vec::new();
+
+    if let Runner::Proton(_) = runner {
+        if let Some(compat_data) = proton_compat_data(cfg, exe, app_id) {
+            env.push((
+                "STEAM_COMPAT_DATA_PATH".to_string(),
+                compat_data.to_string_lossy().into_owned(),
+            ));
+        }
+        if let Some(root) = steam::steam_root() {
+            env.push((
+                "STEAM_COMPAT_CLIENT_INSTALL_PATH".to_string(),
+                root.to_string_lossy().into_owned(),
+            ));
+        }
+        if let Some(app_id) = app_id {
+            env.push(("SteamAppId".to_string(), app_id.to_string()));
+            env.push(("SteamGameId".to_string(), app_id.to_string()));
+        }
+    } else if matches!(runner, Runner::Wine | Runner::Umu) {
+        if let Some(prefix) = wine_prefix(cfg) {
+            env.push((
+                "WINEPREFIX".to_string(),
+                prefix.to_string_lossy().into_owned(),
+            ));
+        }
+        if *runner == Runner::Umu {
+            env.extend(umu_env(cfg, app_id));
+        }
+    }
+
+    let mut user_env: Vec<_> = cfg.env.iter().collect();
+    user_env.sort();
+    for (key, value) in user_env {
+        env.retain(|(k, _)| k != key);
+        env.push((key.clone(), value.clone()));
+    }
+
+    env
+}
+
+/// Variables read by umu-run, logged before launching to make umu issues debuggable
+pub const UMU_ENV_KEYS: [&str; 4] = ["GAMEID", "STORE", "PROTONPATH", "WINEPREFIX"];
+
+/// umu-launcher settings so the protonfixes for the game get applied.
+///
+/// `proton_version` is passed as `PROTONPATH`, names umu resolves on its own
+/// such as `GE-Proton` are passed through unchanged.
+pub fn umu_env(cfg: &Config, app_id: Option<u32>) -> Vec<(String, String)> {
+    let mut env = Vec::new();
+
+    match app_id {
+        Some(app_id) => {
+            env.push(("GAMEID".to_string(), format!("umu-{app_id}")));
+            env.push(("STORE".to_string(), "steam".to_string()));
+        }
+        None => env.push(("GAMEID".to_string(), "umu-default".to_string())),
+    }
+
+    let version = cfg.proton_version.trim();
+    if !version.is_empty() {
+        let proton_path = steam::find_proton(version)
+            .map(|path| path.to_string_lossy().into_owned())
+            .unwrap_or_else(|| version.to_string());
+        env.push(("PROTONPATH".to_string(), proton_path));
+    }
+
+    env
+}
+
+/// Create the prefix referenced by the launch environment if it doesn't exist yet.
+///
+/// Native and custom runners don't use a prefix managed by the launcher.
+pub fn init_prefix(runner: &Runner, env: &[(String, String)]) -> Result<(), String> {
+    if matches!(runner, Runner::Native | Runner::Custom(_)) {
+        return Ok(());
+    }
+    let Some((key, prefix)) = env
+        .iter()
+        .find(|(k, _)| k == "WINEPREFIX" || k == "STEAM_COMPAT_DATA_PATH")
+    else {
+        return Ok(());
+    };
+    let prefix = Path::new(prefix);
+
+    if prefix.join("system.reg").exists() || prefix.join("pfx").exists() {
+        return Ok(());
+    }
+
+    std::fs::create_dir_all(prefix)
+        .map_err(|e| format!("Failed to create {key} {}: {e}", prefix.display()))?;
+
+    // umu-run and proton set up their prefixes on their own
+    if *runner != Runner::Wine {
+        return Ok(());
+    }
+
+    crate::println_info!("Initializing wine prefix {}", prefix.display());
+    let status = std::process::Command::new("wine")
+        .args(["wineboot", "--init"])
+        .envs(env.iter().cloned())
+        .status()
+        .map_err(|e| format!("Failed to run wineboot: {e}"))?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("wineboot exited with {status}"))
+    }
+}
@@ -0,0 +1,535 @@
+use crate::structs::SavedServer;
+use std::{
+    collections::HashMap,
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+/// Prefix of every connectionless packet
+pub const OOB: &[u8] = b"\xff\xff\xff\xff";
+
+/// Game name and protocol the master server lists servers under, by client or engine
+pub fn master_game(client: &str) -> Option<(&'static str, u32)> {
+    if client.starts_with("iw6") {
+        Some(("IW6", 1))
+    } else if client.starts_with("s1") {
+        Some(("S1", 1))
+    } else {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Player {
+    pub name: String,
+    pub score: i32,
+    pub ping: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerInfo {
+    pub address: SocketAddr,
+    pub hostname: String,
+    pub map: String,
+    pub gametype: String,
+    pub clients: u32,
+    pub bots: u32,
+    pub max_clients: u32,
+    pub ping: u32,
+    pub players: Vec<Player>,
+}
+
+impl ServerInfo {
+    fn from_info(address: SocketAddr, info: &HashMap<String, String>, ping: u32) -> Self {
+        let number = |key: &str| info.get(key).and_then(|v| v.parse().ok()).unwrap_or(0);
+        let text = |key: &str| info.get(key).cloned().unwrap_or_default();
+        Self {
+            address,
+            hostname: strip_colors(&text("hostname")),
+            map: text("mapname"),
+            gametype: text("gametype"),
+            clients: number("clients"),
+            bots: number("bots"),
+            max_clients: number("sv_maxclients"),
+            ping,
+            players: Vec::new(),
+        }
+    }
+
+    /// Build the info from a status response, which has the server cvars instead of the info keys
+    fn from_status(
+        address: SocketAddr,
+        info: &HashMap<String, String>,
+        clients: usize,
+        ping: u32,
+    ) -> Self {
+        let text = |key: &str| info.get(key).cloned().unwrap_or_default();
+        Self {
+            address,
+            hostname: strip_colors(&text("sv_hostname")),
+            map: text("mapname"),
+            gametype: text("g_gametype"),
+            clients: clients as u32,
+            bots: 0,
+            max_clients: text("sv_maxclients").parse().unwrap_or(0),
+            ping,
+            players: Vec::new(),
+        }
+    }
+}
+
+/// Remove `^<digit>` color codes from a name
+pub fn strip_colors(text: &str) -> String {
+    let mut result = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '^' && chars.peek().is_some_and(|n| n.is_ascii_digit()) {
+            chars.next();
+            continue;
+        }
+        result.push(c);
+    }
+    result
+}
+
+/// Parse a `\key\value\key\value` info string
+pub fn parse_info_string(info: &str) -> HashMap<String, String> {
+    let mut parts = info.trim_start_matches('\\').split('\\');
+    let mut result = HashMap::new();
+    while let (Some(key), Some(value)) = (parts.next(), parts.next()) {
+        result.insert(key.to_string(), value.to_string());
+    }
+    result
+}
+
+/// Strip the out-of-band header and response name, along with the newline following it if any
+pub fn oob_payload<'a>(data: &'a [u8], name: &str) -> Option<&'a [u8]> {
+    let data = data.strip_prefix(OOB)?.strip_prefix(name.as_bytes())?;
+    Some(data.strip_prefix(b"\n").unwrap_or(data))
+}
+
+pub fn parse_info_response(data: &[u8]) -> Option<HashMap<String, String>> {
+    let payload = oob_payload(data, "infoResponse")?;
+    Some(parse_info_string(&String::from_utf8_lossy(payload)))
+}
+
+/// Parse a status response into the server info string and the player list
+pub fn parse_status_response(data: &[u8]) -> Option<(HashMap<String, String>, Vec<Player>)> {
+    let payload = String::from_utf8_lossy(oob_payload(data, "statusResponse")?).into_owned();
+    let mut lines = payload.lines();
+    let info = parse_info_string(lines.next().unwrap_or_default());
+
+    // player lines look like `<score> <ping> "<name>"`
+    let players = lines
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, ' ');
+            let score = fields.next()?.parse().ok()?;
+            let ping = fields.next()?.parse().ok()?;
+            let name = fields.next()?.trim().trim_matches('"');
+            Some(Player {
+                name: strip_colors(name),
+                score,
+                ping,
+            })
+        })
+        .collect();
+
+    Some((info, players))
+}
+
+/// Parse a `getserversResponse` packet into server addresses.
+///
+/// Returns the addresses and whether the `EOT` marker ending the list was seen.
+pub fn parse_servers_response(data: &[u8]) -> Option<(Vec<SocketAddr>, bool)> {
+    let mut data = oob_payload(data, "getserversResponse")?;
+    let mut servers = Vec::new();
+
+    while let Some(rest) = data.strip_prefix(b"\\") {
+        if rest.starts_with(b"EOT") {
+            return Some((servers, true));
+        }
+        let Some(entry) = rest.get(..6) else {
+            break;
+        };
+        let ip = std::net::Ipv4Addr::new(entry[0], entry[1], entry[2], entry[3]);
+        let port = u16::from_be_bytes([entry[4], entry[5]]);
+        if port != 0 && !ip.is_unspecified() {
+            servers.push(SocketAddr::from((ip, port)));
+        }
+        data = &rest[6..];
+    }
+
+    Some((servers, false))
+}
+
+pub fn resolve(address: &str) -> Result<SocketAddr, String> {
+    address
+        .to_socket_addrs()
+        .map_err(|e| format!("Failed to resolve {address}: {e}"))?
+        .find(SocketAddr::is_ipv4)
+        .ok_or_else(|| format!("No IPv4 address found for {address}"))
+}
+
+/// Ask the master server for the servers of `game`
+pub fn query_master(
+    master: &str,
+    game: &str,
+    protocol: u32,
+    timeout: Duration,
+) -> Result<Vec<SocketAddr>, String> {
+    let master = resolve(master)?;
+    let socket = UdpSocket::bind("0.0.0.0:0").map_err(|e| format!("Failed to bind socket: {e}"))?;
+    socket
+        .send_to(
+            &[
+                OOB,
+                format!("getservers {game} {protocol} full empty").as_bytes(),
+            ]
+            .concat(),
+            master,
+        )
+        .map_err(|e| format!("Failed to query master server {master}: {e}"))?;
+
+    // the list is split over several packets when there are many servers
+    let deadline = Instant::now() + timeout;
+    let mut servers = Vec::new();
+    let mut responded = false;
+    let mut buffer = [0u8; 65536];
+    while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+        socket
+            .set_read_timeout(Some(remaining.max(Duration::from_millis(1))))
+            .ok();
+        let Ok((len, from)) = socket.recv_from(&mut buffer) else {
+            break;
+        };
+        if from != master {
+            continue;
+        }
+        if let Some((mut list, done)) = parse_servers_response(&buffer[..len]) {
+            responded = true;
+            servers.append(&mut list);
+            if done {
+                break;
+            }
+        }
+    }
+
+    if !responded {
+        return Err(format!("No response from master server {master}"));
+    }
+    servers.sort();
+    servers.dedup();
+    Ok(servers)
+}
+
+/// Send `getinfo` and `getstatus` to all servers at once and collect the answers until `timeout`
+pub fn query_servers(servers: &[SocketAddr], timeout: Duration) -> Vec<ServerInfo> {
+    query(servers, timeout, true)
+}
+
+/// Like [`query_servers`] with `getstatus` only, which every server answers including LAN servers
+/// that aren't listed on the master server
+pub fn query_status(servers: &[SocketAddr], timeout: Duration) -> Vec<ServerInfo> {
+    query(servers, timeout, false)
+}
+
+fn query(servers: &[SocketAddr], timeout: Duration, with_info: bool) -> Vec<ServerInfo> {
+    let Ok(socket) = UdpSocket::bind("0.0.0.0:0") else {
+        return Vec::new();
+    };
+
+    let challenge = format!(
+        "{:x}",
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default()
+    );
+    let getinfo = [OOB, format!("getinfo {challenge}").as_bytes()].concat();
+    let getstatus = [OOB, format!("getstatus {challenge}").as_bytes()].concat();
+
+    let start = Instant::now();
+    for server in servers {
+        let result = if with_info {
+            socket.send_to(&getinfo, server)
+        } else {
+            Ok(0)
+        };
+        if let Err(error) = result.and_then(|_| socket.send_to(&getstatus, server)) {
+            debug!("Failed to query {server}: {error}");
+        }
+    }
+
+    let deadline = start + timeout;
+    let mut infos: HashMap<SocketAddr, ServerInfo> = HashMap::new();
+    let mut players: HashMap<SocketAddr, Vec<Player>> = HashMap::new();
+    let mut buffer = [0u8; 65536];
+    while (with_info && infos.len() < servers.len()) || players.len() < servers.len() {
+        let Some(remaining) = deadline.checked_duration_since(Instant::now()) else {
+            break;
+        };
+        socket
+            .set_read_timeout(Some(remaining.max(Duration::from_millis(1))))
+            .ok();
+        let Ok((len, from)) = socket.recv_from(&mut buffer) else {
+            break;
+        };
+        if !servers.contains(&from) {
+            continue;
+        }
+        let data = &buffer[..len];
+
+        if let Some(info) = parse_info_response(data) {
+            if info.get("challenge").is_some_and(|c| *c == challenge) {
+                let ping = start.elapsed().as_millis() as u32;
+                infos.insert(from, ServerInfo::from_info(from, &info, ping));
+            }
+        } else if let Some((info, list)) = parse_status_response(data) {
+            if info.get("challenge").is_none_or(|c| *c == challenge) {
+                if !with_info {
+                    let ping = start.elapsed().as_millis() as u32;
+                    infos.insert(from, ServerInfo::from_status(from, &info, list.len(), ping));
+                }
+                players.insert(from, list);
+            }
+        }
+    }
+
+    let mut result: Vec<ServerInfo> = infos
+        .into_values()
+        .map(|mut info| {
+            info.players = players.remove(&info.address).unwrap_or_default();
+            info
+        })
+        .collect();
+    result.sort_by_key(|info| info.address);
+    result
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortKey {
+    Players,
+    Ping,
+    Name,
+    Map,
+}
+
+impl std::str::FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "players" => Ok(Self::Players),
+            "ping" => Ok(Self::Ping),
+            "name" | "hostname" => Ok(Self::Name),
+            "map" => Ok(Self::Map),
+            other => Err(format!(
+                "Unknown sort key '{other}', expected one of players, ping, name, map"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    /// Matched case-insensitively against hostname, map and gametype
+    pub text: String,
+    pub not_empty: bool,
+    pub not_full: bool,
+}
+
+impl Filter {
+    pub fn matches(&self, server: &ServerInfo) -> bool {
+        if self.not_empty && server.clients == 0 {
+            return false;
+        }
+        if self.not_full
+            && server.max_clients > 0
+            && server.clients + server.bots >= server.max_clients
+        {
+            return false;
+        }
+
+        let text = self.text.to_lowercase();
+        text.is_empty()
+            || [&server.hostname, &server.map, &server.gametype]
+                .iter()
+                .any(|field| field.to_lowercase().contains(&text))
+    }
+}
+
+/// Apply the filter and sort, most players and lowest ping first unless sorted by name or map
+pub fn filter_sort(servers: Vec<ServerInfo>, filter: &Filter, sort: SortKey) -> Vec<ServerInfo> {
+    let mut servers: Vec<ServerInfo> = servers.into_iter().filter(|s| filter.matches(s)).collect();
+    match sort {
+        SortKey::Players => {
+            servers.sort_by(|a, b| b.clients.cmp(&a.clients).then(a.ping.cmp(&b.ping)))
+        }
+        SortKey::Ping => servers.sort_by_key(|s| s.ping),
+        SortKey::Name => servers.sort_by_key(|s| s.hostname.to_lowercase()),
+        SortKey::Map => servers.sort_by(|a, b| a.map.cmp(&b.map).then(b.clients.cmp(&a.clients))),
+    }
+    servers
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() > width {
+        format!("{}~", text.chars().take(width - 1).collect::<String>())
+    } else {
+        text.to_string()
+    }
+}
+
+/// Print the server list as a numbered table
+pub fn print_table(servers: &[ServerInfo]) {
+    println!(
+        "{:>3}  {:<40} {:<16} {:<8} {:>9} {:>5}",
+        "#", "Hostname", "Map", "Mode", "Players", "Ping"
+    );
+    for (i, server) in servers.iter().enumerate() {
+        let players = if server.bots > 0 {
+            format!("{}+{}/{}", server.clients, server.bots, server.max_clients)
+        } else {
+            format!("{}/{}", server.clients, server.max_clients)
+        };
+        println!(
+            "{:>3}  {:<40} {:<16} {:<8} {:>9} {:>5}",
+            i,
+            truncate(&server.hostname, 40),
+            truncate(&server.map, 16),
+            truncate(&server.gametype, 8),
+            players,
+            server.ping
+        );
+    }
+}
+
+/// Short relative time like `5m ago` for a duration in seconds
+pub fn format_age(seconds: u64) -> String {
+    match seconds {
+        0..60 => "just now".to_string(),
+        60..3600 => format!("{}m ago", seconds / 60),
+        3600..86400 => format!("{}h ago", seconds / 3600),
+        _ => format!("{}d ago", seconds / 86400),
+    }
+}
+
+/// Print saved servers with their live status, `None` marks servers that didn't answer
+pub fn print_saved_table(saved: &[SavedServer], status: &[Option<&ServerInfo>], now: u64) {
+    println!(
+        "{:>3}  {:<1} {:<32} {:<21} {:<16} {:>7} {:>7} {:<10} {:<16}",
+        "#", "", "Hostname", "Address", "Map", "Players", "Ping", "Played", "Tags"
+    );
+    for (i, (server, info)) in saved.iter().zip(status).enumerate() {
+        let hostname = info.map_or(server.hostname.as_str(), |info| info.hostname.as_str());
+        let (map, players, ping) = match info {
+            Some(info) => (
+                info.map.as_str(),
+                format!("{}/{}", info.clients, info.max_clients),
+                info.ping.to_string(),
+            ),
+            None => ("", String::new(), "offline".to_string()),
+        };
+        let played = if server.last_played == 0 {
+            String::new()
+        } else {
+            format_age(now.saturating_sub(server.last_played))
+        };
+        println!(
+            "{:>3}  {:<1} {:<32} {:<21} {:<16} {:>7} {:>7} {:<10} {:<16}",
+            i,
+            if server.favorite { "*" } else { "" },
+            truncate(hostname, 32),
+            truncate(&server.address, 21),
+            truncate(map, 16),
+            players,
+            ping,
+            played,
+            server.tags.join(",")
+        );
+    }
+}
+
+/// Ask which of the listed servers to join, exits if the user just presses enter
+fn pick(count: usize) -> usize {
+    println!("Select a server to join, or press enter to exit:");
+    crate::misc::stdin_number_or_empty(|&index: &usize| index < count)
+        .unwrap_or_else(|| std::process::exit(0))
+}
+
+/// Show the server list of `master` for the client and return the address and name of the server picked to join
+pub fn browse(
+    client: &str,
+    master: &str,
+    filter: &Filter,
+    sort: SortKey,
+) -> (String, Option<String>) {
+    let Some((game, protocol)) = master_game(client) else {
+        crate::println_error!(
+            "The server browser supports the IW6 and S1 clients, specify one, e.g. alterware-launcher servers iw6-mod"
+        );
+        std::process::exit(1);
+    };
+
+    crate::println_info!("Fetching {game} servers from {master}");
+    let addresses =
+        query_master(master, game, protocol, Duration::from_secs(5)).unwrap_or_else(|error| {
+            crate::println_error!("{error}");
+            crate::misc::stdin();
+            std::process::exit(1);
+        });
+    info!("Master server listed {} servers", addresses.len());
+
+    let list = filter_sort(
+        query_servers(&addresses, Duration::from_secs(2)),
+        filter,
+        sort,
+    );
+    if list.is_empty() {
+        crate::println_info!("No servers found");
+        std::process::exit(0);
+    }
+
+    print_table(&list);
+    let server = &list[pick(list.len())];
+    crate::println_info!("Joining {} ({})", server.hostname, server.address);
+    for player in &server.players {
+        println!(
+            "    {:<32} {:>6} {:>4}ms",
+            player.name, player.score, player.ping
+        );
+    }
+    (server.address.to_string(), Some(server.hostname.clone()))
+}
+
+/// Show favorite and recent servers with their live status and return the one picked to join.
+///
+/// Servers are queried directly, so this also works for LAN servers in offline mode.
+pub fn browse_saved(state_dir: &Path, tag: Option<&str>) -> (String, Option<String>) {
+    let saved: Vec<SavedServer> = crate::cache::get_saved_servers(state_dir)
+        .into_iter()
+        .filter(|s| tag.is_none_or(|tag| s.tags.iter().any(|t| t == tag)))
+        .collect();
+    if saved.is_empty() {
+        crate::println_info!(
+            "No saved servers, join one or add it with: alterware-launcher servers favorite <host:port> [tags]"
+        );
+        std::process::exit(0);
+    }
+
+    let resolved: Vec<_> = saved.iter().map(|s| resolve(&s.address).ok()).collect();
+    let addresses: Vec<_> = resolved.iter().flatten().copied().collect();
+    let live = query_status(&addresses, Duration::from_secs(2));
+    let status: Vec<_> = resolved
+        .iter()
+        .map(|address| live.iter().find(|info| Some(info.address) == *address))
+        .collect();
+
+    print_saved_table(&saved, &status, crate::cache::unix_time());
+    let index = pick(saved.len());
+    let server = &saved[index];
+    let hostname = status[index].map(|info| info.hostname.clone());
+    crate::println_info!("Joining {}", server.address);
+    (server.address.clone(), hostname)
+}
@@ -0,0 +1,598 @@
+// Proton, shortcuts and the compatibility tool are only used on Linux
+#![cfg_attr(windows, allow(dead_code))]
+
+use crate::misc;
+#[cfg(unix)]
+use crate::structs::Config;
+use crate::vdf::{self, Vdf};
+use std::{
+    cmp::Ordering,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Steam root directories relative to the home directory; native, Flatpak and Snap
+#[cfg(unix)]
+const STEAM_ROOTS: [&str; 6] = [
+    ".steam/root",
+    ".steam/steam",
+    ".local/share/Steam",
+    ".var/app/com.valvesoftware.Steam/.local/share/Steam",
+    ".var/app/com.valvesoftware.Steam/data/Steam",
+    "snap/steam/common/.local/share/Steam",
+];
+
+/// System wide directories for custom Proton builds
+#[cfg(unix)]
+const SYSTEM_COMPAT_TOOLS: [&str; 2] = [
+    "/usr/share/steam/compatibilitytools.d",
+    "/usr/local/share/steam/compatibilitytools.d",
+];
+
+/// The Steam installation registered in the Windows registry
+#[cfg(windows)]
+pub fn steam_roots() -> Vec<PathBuf> {
+    match steamlocate::SteamDir::locate() {
+        Ok(steam_dir) => vec![steam_dir.path().to_path_buf()],
+        Err(e) => {
+            warn!("Failed to locate Steam: {e}");
+            Vec::new()
+        }
+    }
+}
+
+/// All Steam installations found on this system, without duplicates
+#[cfg(unix)]
+pub fn steam_roots() -> Vec<PathBuf> {
+    let Some(home) = std::env::var_os("HOME") else {
+        return Vec::new();
+    };
+    let home = PathBuf::from(home);
+
+    let mut roots: Vec<PathBuf> = Vec::new();
+    for root in STEAM_ROOTS {
+        let path = home.join(root);
+        if !path.join("steamapps").is_dir() {
+            continue;
+        }
+        let path = path.canonicalize().unwrap_or(path);
+        if !roots.contains(&path) {
+            roots.push(path);
+        }
+    }
+    roots
+}
+
+/// The primary Steam installation
+pub fn steam_root() -> Option<PathBuf> {
+    steam_roots().into_iter().next()
+}
+
+/// Library paths listed in a libraryfolders.vdf, supports the old and new format
+pub fn parse_library_folders(content: &str) -> Vec<PathBuf> {
+    let Ok(vdf) = vdf::parse(content) else {
+        return Vec::new();
+    };
+    let Some(folders) = vdf.get("libraryfolders") else {
+        return Vec::new();
+    };
+
+    folders
+        .entries()
+        .iter()
+        .filter(|(key, _)| key.parse::<u32>().is_ok())
+        .filter_map(|(_, value)| value.as_str().or_else(|| value.get("path")?.as_str()))
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Every Steam library of every Steam installation, including the installations themselves
+pub fn library_folders() -> Vec<PathBuf> {
+    let mut libraries: Vec<PathBuf> = Vec::new();
+    for root in steam_roots() {
+        let vdf_path = root.join("steamapps").join("libraryfolders.vdf");
+        let mut found = vec![root.clone()];
+        match std::fs::read_to_string(&vdf_path) {
+            Ok(content) => found.extend(parse_library_folders(&content)),
+            Err(e) => warn!("Failed to read {}: {e}", vdf_path.display()),
+        }
+
+        for library in found {
+            if !library.join("steamapps").is_dir() {
+                continue;
+            }
+            let library = library.canonicalize().unwrap_or(library);
+            if !libraries.contains(&library) {
+                libraries.push(library);
+            }
+        }
+    }
+    libraries
+}
+
+/// Install directory name from the content of an appmanifest_<app_id>.acf
+pub fn parse_app_manifest(content: &str) -> Option<String> {
+    vdf::parse(content)
+        .ok()?
+        .path(&["AppState", "installdir"])?
+        .as_str()
+        .map(String::from)
+}
+
+/// Find the install directory of `app_id` in the given libraries
+pub fn find_app(libraries: &[PathBuf], app_id: u32) -> Option<PathBuf> {
+    libraries.iter().find_map(|library| {
+        let steamapps = library.join("steamapps");
+        let manifest = steamapps.join(format!("appmanifest_{app_id}.acf"));
+        let install_dir = parse_app_manifest(&std::fs::read_to_string(manifest).ok()?)?;
+        let path = steamapps.join("common").join(install_dir);
+        path.is_dir().then_some(path)
+    })
+}
+
+/// Proton installations as (name, directory), sorted from oldest to newest
+#[cfg(unix)]
+pub fn proton_installs() -> Vec<(String, PathBuf)> {
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    for library in library_folders() {
+        dirs.push(library.join("steamapps").join("common"));
+    }
+    for root in steam_roots() {
+        dirs.push(root.join("compatibilitytools.d"));
+    }
+    dirs.extend(SYSTEM_COMPAT_TOOLS.iter().map(PathBuf::from));
+
+    let mut installs: Vec<(String, PathBuf)> = Vec::new();
+    for dir in dirs {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if path.join("proton").is_file() && !installs.iter().any(|(n, _)| *n == name) {
+                installs.push((name, path));
+            }
+        }
+    }
+
+    installs.sort_by(|a, b| natural_cmp(&a.0, &b.0));
+    installs
+}
+
+/// Find a Proton install by directory name or path, or the newest one if `version` is empty
+#[cfg(unix)]
+pub fn find_proton(version: &str) -> Option<PathBuf> {
+    let version = version.trim();
+    if !version.is_empty() {
+        let path = crate::misc::expand_home(version);
+        if path.join("proton").is_file() {
+            return Some(path);
+        }
+        if path.is_file() && path.ends_with("proton") {
+            return path.parent().map(Path::to_path_buf);
+        }
+    }
+
+    let installs = proton_installs();
+    if version.is_empty() {
+        return installs.into_iter().last().map(|(_, path)| path);
+    }
+    installs
+        .into_iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(version))
+        .map(|(_, path)| path)
+}
+
+/// Compat data directory Steam created for `app_id` in the library containing `game_dir`
+pub fn compat_data_path(game_dir: &Path, app_id: u32) -> Option<PathBuf> {
+    game_dir
+        .ancestors()
+        .find(|dir| dir.file_name().is_some_and(|name| name == "steamapps"))
+        .map(Path::to_path_buf)
+        .or_else(|| steam_root().map(|root| root.join("steamapps")))
+        .map(|steamapps| steamapps.join("compatdata").join(app_id.to_string()))
+}
+
+/// Compare strings so that embedded numbers are ordered by value, e.g. 9 < 10
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let take_number = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                    let mut number = String::new();
+                    while let Some(c) = chars.peek().filter(|c| c.is_ascii_digit()) {
+                        number.push(*c);
+                        chars.next();
+                    }
+                    number.trim_start_matches('0').to_string()
+                };
+                let (x, y) = (take_number(&mut a), take_number(&mut b));
+                let ordering = x.len().cmp(&y.len()).then_with(|| x.cmp(&y));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+/// Steam user directories (`userdata/<account id>`) of every Steam installation
+pub fn user_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    for root in steam_roots() {
+        let Ok(entries) = std::fs::read_dir(root.join("userdata")) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.parse::<u32>().is_ok_and(|id| id != 0) && entry.path().is_dir() {
+                dirs.push(entry.path());
+            }
+        }
+    }
+    dirs
+}
+
+/// CRC-32 (IEEE) as used by Steam to derive shortcut app ids
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffffffffu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb88320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// A non-Steam game entry in shortcuts.vdf
+pub struct Shortcut {
+    pub app_name: String,
+    pub exe: PathBuf,
+    pub start_dir: PathBuf,
+    pub launch_options: String,
+    pub icon: Option<PathBuf>,
+}
+
+impl Shortcut {
+    fn quoted_exe(&self) -> String {
+        format!("\"{}\"", self.exe.display())
+    }
+
+    /// App id Steam assigns to the shortcut, also used for grid artwork file names
+    pub fn app_id(&self) -> u32 {
+        let key = format!("{}{}", self.quoted_exe(), self.app_name);
+        crc32(key.as_bytes()) | 0x80000000
+    }
+
+    fn to_vdf(&self) -> Vdf {
+        let string = |value: &str| Vdf::Value(value.to_string());
+        Vdf::Map(vec![
+            ("appid".to_string(), Vdf::Int(self.app_id())),
+            ("AppName".to_string(), string(&self.app_name)),
+            ("Exe".to_string(), string(&self.quoted_exe())),
+            (
+                "StartDir".to_string(),
+                string(&format!("\"{}\"", self.start_dir.display())),
+            ),
+            (
+                "icon".to_string(),
+                string(
+                    &self
+                        .icon
+                        .as_ref()
+                        .map(|icon| icon.to_string_lossy().into_owned())
+                        .unwrap_or_default(),
+                ),
+            ),
+            ("ShortcutPath".to_string(), string("")),
+            ("LaunchOptions".to_string(), string(&self.launch_options)),
+            ("IsHidden".to_string(), Vdf::Int(0)),
+            ("AllowDesktopConfig".to_string(), Vdf::Int(1)),
+            ("AllowOverlay".to_string(), Vdf::Int(1)),
+            ("OpenVR".to_string(), Vdf::Int(0)),
+            ("Devkit".to_string(), Vdf::Int(0)),
+            ("DevkitGameID".to_string(), string("")),
+            ("DevkitOverrideAppID".to_string(), Vdf::Int(0)),
+            ("LastPlayTime".to_string(), Vdf::Int(0)),
+            ("FlatpakAppID".to_string(), string("")),
+            ("tags".to_string(), Vdf::Map(Vec::new())),
+        ])
+    }
+}
+
+/// Add shortcuts to a parsed shortcuts.vdf, skipping those that already exist.
+///
+/// Returns the shortcuts that were added.
+pub fn add_shortcuts<'a>(root: &mut Vdf, shortcuts: &'a [Shortcut]) -> Vec<&'a Shortcut> {
+    let Vdf::Map(root_entries) = root else {
+        return Vec::new();
+    };
+    if !root_entries
+        .iter()
+        .any(|(k, _)| k.eq_ignore_ascii_case("shortcuts"))
+    {
+        root_entries.push(("shortcuts".to_string(), Vdf::Map(Vec::new())));
+    }
+    let Some(Vdf::Map(entries)) = root_entries
+        .iter_mut()
+        .find(|(k, _)| k.eq_ignore_ascii_case("shortcuts"))
+        .map(|(_, v)| v)
+    else {
+        return Vec::new();
+    };
+
+    let mut added = Vec::new();
+    for shortcut in shortcuts {
+        let exists = entries.iter().any(|(_, entry)| {
+            entry.get("Exe").and_then(Vdf::as_str) == Some(shortcut.quoted_exe().as_str())
+                && entry.get("LaunchOptions").and_then(Vdf::as_str)
+                    == Some(shortcut.launch_options.as_str())
+        });
+        if exists {
+            continue;
+        }
+
+        let index = entries
+            .iter()
+            .filter_map(|(k, _)| k.parse::<usize>().ok())
+            .max()
+            .map_or(0, |i| i + 1);
+        entries.push((index.to_string(), shortcut.to_vdf()));
+        added.push(shortcut);
+    }
+    added
+}
+
+/// Register shortcuts in the shortcuts.vdf of a Steam user, copying grid artwork if available.
+///
+/// Artwork is looked up in `grid_dir` as `<client>.png` (wide), `<client>p.png` (portrait),
+/// `<client>_hero.png` and `<client>_logo.png`, where client is the shortcut's app name.
+pub fn register_shortcuts(
+    user_dir: &Path,
+    shortcuts: &[Shortcut],
+    grid_dir: Option<&Path>,
+) -> Result<usize, String> {
+    let config_dir = user_dir.join("config");
+    let vdf_path = config_dir.join("shortcuts.vdf");
+
+    let mut root = match std::fs::read(&vdf_path) {
+        Ok(data) => vdf::parse_binary(&data)
+            .map_err(|e| format!("Failed to parse {}: {e}", vdf_path.display()))?,
+        Err(_) => Vdf::Map(Vec::new()),
+    };
+
+    let added = add_shortcuts(&mut root, shortcuts);
+    if added.is_empty() {
+        return Ok(0);
+    }
+
+    std::fs::create_dir_all(&config_dir)
+        .map_err(|e| format!("Failed to create {}: {e}", config_dir.display()))?;
+    if vdf_path.exists() {
+        let backup = vdf_path.with_extension("vdf.bak");
+        std::fs::copy(&vdf_path, &backup)
+            .map_err(|e| format!("Failed to back up {}: {e}", vdf_path.display()))?;
+    }
+    std::fs::write(&vdf_path, vdf::to_binary(&root))
+        .map_err(|e| format!("Failed to write {}: {e}", vdf_path.display()))?;
+
+    if let Some(grid_dir) = grid_dir {
+        let grid = config_dir.join("grid");
+        for shortcut in added.iter() {
+            let app_id = shortcut.app_id();
+            for suffix in ["", "p", "_hero", "_logo"] {
+                let source = grid_dir.join(format!("{}{suffix}.png", shortcut.app_name));
+                if !source.is_file() {
+                    continue;
+                }
+                std::fs::create_dir_all(&grid)
+                    .map_err(|e| format!("Failed to create {}: {e}", grid.display()))?;
+                let target = grid.join(format!("{app_id}{suffix}.png"));
+                std::fs::copy(&source, &target)
+                    .map_err(|e| format!("Failed to copy {}: {e}", source.display()))?;
+            }
+        }
+    }
+
+    Ok(added.len())
+}
+
+/// Name of the compatibility tool directory and internal tool name
+pub const COMPAT_TOOL_NAME: &str = "alterware";
+
+/// Steam Linux Runtime 3.0 (sniper), required by Proton 8 and newer
+const SNIPER_APP_ID: &str = "1628350";
+
+/// Verbs Steam passes to compatibility tools to start the game
+pub const COMPAT_RUN_VERBS: [&str; 2] = ["waitforexitandrun", "run"];
+
+/// Content of compatibilitytool.vdf
+pub fn compat_tool_vdf() -> String {
+    let string = |value: &str| Vdf::Value(value.to_string());
+    vdf::to_text(&Vdf::Map(vec![(
+        "compatibilitytools".to_string(),
+        Vdf::Map(vec![(
+            "compat_tools".to_string(),
+            Vdf::Map(vec![(
+                COMPAT_TOOL_NAME.to_string(),
+                Vdf::Map(vec![
+                    ("install_path".to_string(), string(".")),
+                    ("display_name".to_string(), string("AlterWare")),
+                    ("from_oslist".to_string(), string("windows")),
+                    ("to_oslist".to_string(), string("linux")),
+                ]),
+            )]),
+        )]),
+    )]))
+}
+
+/// Content of toolmanifest.vdf, Steam calls `<tool dir>/<launcher> --compat-tool <verb> <command>`
+pub fn tool_manifest_vdf(launcher_name: &str) -> String {
+    let string = |value: &str| Vdf::Value(value.to_string());
+    vdf::to_text(&Vdf::Map(vec![(
+        "manifest".to_string(),
+        Vdf::Map(vec![
+            ("version".to_string(), string("2")),
+            (
+                "commandline".to_string(),
+                string(&format!("/{launcher_name} --compat-tool %verb%")),
+            ),
+            ("require_tool_appid".to_string(), string(SNIPER_APP_ID)),
+            ("use_sessions".to_string(), string("1")),
+        ]),
+    )]))
+}
+
+/// Install the launcher as a Steam compatibility tool, returns the tool directory
+#[cfg(unix)]
+pub fn install_compat_tool(launcher: &Path) -> Result<PathBuf, String> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let home = std::env::var_os("HOME").ok_or("HOME is not set")?;
+    let default_root = Path::new(&home).join(".steam").join("root");
+    let root = if default_root.is_dir() {
+        default_root
+    } else {
+        steam_root().ok_or("No Steam installation found")?
+    };
+
+    let dir = root.join("compatibilitytools.d").join(COMPAT_TOOL_NAME);
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+
+    let launcher_name = "alterware-launcher";
+    let target = dir.join(launcher_name);
+    if launcher != target {
+        std::fs::copy(launcher, &target)
+            .map_err(|e| format!("Failed to copy launcher to {}: {e}", target.display()))?;
+        std::fs::set_permissions(&target, std::fs::Permissions::from_mode(0o755))
+            .map_err(|e| format!("Failed to set permissions for {}: {e}", target.display()))?;
+    }
+
+    for (file, content) in [
+        ("compatibilitytool.vdf", compat_tool_vdf()),
+        ("toolmanifest.vdf", tool_manifest_vdf(launcher_name)),
+    ] {
+        std::fs::write(dir.join(file), content)
+            .map_err(|e| format!("Failed to write {file}: {e}"))?;
+    }
+
+    Ok(dir)
+}
+
+/// Index of the game executable in the command line Steam passes for `%command%`
+pub fn wrapped_exe(command: &[String]) -> Option<usize> {
+    command
+        .iter()
+        .rposition(|arg| arg.to_ascii_lowercase().ends_with(".exe"))
+}
+
+/// Start the game through Steam so the overlay, friends presence and playtime work.
+///
+/// Steam starts the game's own executable, the client and the resolved game args are
+/// staged in the cache for the `--steam-wrap` launch options to pick up.
+pub fn launch(install_path: &Path, client: &str, args: &[String], app_id: u32) {
+    crate::cache::stage_launch(install_path, client, args);
+
+    crate::println_info!("Launching {client} through Steam (app id {app_id})");
+    println!(
+        "Make sure the Steam launch options of the game are set to:\n    {} --steam-wrap %command%",
+        misc::join_args(&[std::env::current_exe()
+            .unwrap()
+            .to_string_lossy()
+            .into_owned()])
+    );
+
+    let url = format!("steam://rungameid/{app_id}");
+    #[cfg(windows)]
+    let result = Command::new("cmd").args(["/C", "start", "", &url]).spawn();
+    #[cfg(unix)]
+    let result = if misc::is_program_in_path("steam") {
+        Command::new("steam")
+            .args(["-applaunch", &app_id.to_string()])
+            .spawn()
+    } else {
+        Command::new("xdg-open").arg(&url).spawn()
+    };
+
+    if let Err(error) = result {
+        crate::println_error!("Failed to start Steam: {error}");
+        misc::stdin();
+    }
+}
+
+/// Run the command Steam prepared with the game executable swapped for the client.
+///
+/// Keeps Steam's runtime, Proton and overlay setup intact, exits with the game's exit code.
+pub fn launch_wrapped(command: &[String], file_path: &Path, args: &[String]) -> ! {
+    let mut command = command.to_vec();
+    match wrapped_exe(&command) {
+        Some(index) => command[index] = file_path.to_string_lossy().into_owned(),
+        None => command.push(file_path.to_string_lossy().into_owned()),
+    }
+    command.extend_from_slice(args);
+
+    info!(
+        "Steam wrapped launch: {}",
+        misc::join_args(&misc::redact_words(&command))
+    );
+    crate::println_info!("Launching {} through Steam", file_path.display());
+    let exit_status = Command::new(&command[0])
+        .args(&command[1..])
+        .current_dir(file_path.parent().unwrap())
+        .spawn()
+        .expect("Failed to launch the game")
+        .wait()
+        .expect("Failed to wait for the game process to finish");
+
+    crate::println_error!("Game exited with {exit_status}");
+    std::process::exit(exit_status.code().unwrap_or(1));
+}
+
+/// Turn the `<verb> <command>` Steam passes to the compatibility tool into a Proton command.
+///
+/// Only the run verbs update and launch the client, everything else is handed to Proton as-is.
+#[cfg(unix)]
+pub fn compat_tool_command(command: Vec<String>, cfg: &Config) -> Vec<String> {
+    let proton = find_proton(&cfg.proton_version).unwrap_or_else(|| {
+        crate::println_error!("No Proton install found for the AlterWare compatibility tool");
+        std::process::exit(1);
+    });
+    let mut proton_command = vec![proton.join("proton").to_string_lossy().into_owned()];
+    proton_command.extend(command);
+    info!(
+        "Compatibility tool command: {}",
+        misc::join_args(&proton_command)
+    );
+
+    let verb = proton_command
+        .get(1)
+        .map(String::as_str)
+        .unwrap_or_default();
+    if !COMPAT_RUN_VERBS.contains(&verb) {
+        let status = Command::new(&proton_command[0])
+            .args(&proton_command[1..])
+            .status()
+            .expect("Failed to run Proton");
+        std::process::exit(status.code().unwrap_or(1));
+    }
+
+    proton_command
+}
@@ -1,5 +1,11 @@
+use crate::extend::CutePath;
+use crate::misc;
 use colored::ColoredString;
-use std::{collections::HashMap, path::Path};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
 
 #[derive(serde::Deserialize, serde::Serialize, Clone)]
 pub struct CdnFile {
@@ -32,6 +38,59 @@ impl Game<'_> {
         }
         true
     }
+
+    /// Apply the renames of files moved by newer versions of the client
+    pub fn rename_files(&self, dir: &Path) {
+        for entry in self.rename.iter() {
+            let file_path = dir.join(entry.0);
+            let new_path = dir.join(entry.1);
+            if file_path.exists() {
+                if fs::rename(&file_path, &new_path).is_err() {
+                    println!(
+                        "{}Couldn't rename {} -> {}",
+                        misc::prefix("error"),
+                        file_path.cute_path(),
+                        new_path.cute_path()
+                    );
+                } else {
+                    println!(
+                        "{}{} -> {}",
+                        misc::prefix("renamed"),
+                        file_path.cute_path(),
+                        new_path.cute_path()
+                    );
+                }
+            }
+        }
+    }
+
+    /// Remove files that newer versions of the client no longer use
+    pub fn delete_files(&self, dir: &Path) {
+        for f in self.delete.iter() {
+            let file_path = dir.join(f);
+            if file_path.is_file() {
+                if fs::remove_file(&file_path).is_err() {
+                    println!(
+                        "{}Couldn't delete {}",
+                        misc::prefix("error"),
+                        file_path.cute_path()
+                    );
+                } else {
+                    println!("{}{}", misc::prefix("removed"), file_path.cute_path());
+                }
+            } else if file_path.is_dir() {
+                if fs::remove_dir_all(&file_path).is_err() {
+                    println!(
+                        "{}Couldn't delete {}",
+                        misc::prefix("error"),
+                        file_path.cute_path()
+                    );
+                } else {
+                    println!("{}{}", misc::prefix("removed"), file_path.cute_path());
+                }
+            }
+        }
+    }
 }
 
 #[derive(serde::Deserialize, serde::Serialize, PartialEq, Debug, Clone)]
@@ -54,6 +113,38 @@ pub struct Config {
     pub cdn_url: String,
     #[serde(default)]
     pub offline: bool,
+    #[serde(default)]
+    pub runner: String,
+    #[serde(default)]
+    pub runner_command: String,
+    #[serde(default)]
+    pub launch_command: String,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    #[serde(default)]
+    pub wine_prefix: String,
+    #[serde(default)]
+    pub manage_prefix: bool,
+    #[serde(default)]
+    pub proton_version: String,
+    #[serde(default)]
+    pub steam_launch: bool,
+    #[serde(default)]
+    pub master_server: String,
+    #[serde(default)]
+    pub rcon_password: String,
+    #[serde(default)]
+    pub supervise: bool,
+    #[serde(default)]
+    pub supervisor: SupervisorConfig,
+    #[serde(default)]
+    pub instances: Vec<ServerInstance>,
+    #[serde(default)]
+    pub webhooks: Vec<Webhook>,
+    #[serde(default)]
+    pub hooks: Hooks,
+    #[serde(default)]
+    pub game_log: GameLogConfig,
 }
 
 impl Default for Config {
@@ -71,10 +162,190 @@ impl Default for Config {
             prerelease: false,
             cdn_url: String::default(),
             offline: false,
+            runner: String::default(),
+            runner_command: String::default(),
+            launch_command: String::default(),
+            env: HashMap::default(),
+            wine_prefix: String::default(),
+            manage_prefix: false,
+            proton_version: String::default(),
+            steam_launch: false,
+            master_server: String::default(),
+            rcon_password: String::default(),
+            supervise: false,
+            supervisor: SupervisorConfig::default(),
+            instances: Vec::new(),
+            webhooks: Vec::new(),
+            hooks: Hooks::default(),
+            game_log: GameLogConfig::default(),
+        }
+    }
+}
+
+/// Restart and log settings used in supervisor mode
+#[derive(serde::Deserialize, serde::Serialize, PartialEq, Debug, Clone)]
+#[serde(default)]
+pub struct SupervisorConfig {
+    /// Crashes allowed within `restart_window` seconds before giving up
+    pub max_restarts: u32,
+    pub restart_window: u64,
+    /// Delay before the first restart in seconds, doubled for every further crash in the window
+    pub backoff_initial: u64,
+    pub backoff_max: u64,
+    /// Directory for the server logs, relative to the install path
+    pub log_dir: String,
+    /// Size in bytes at which a log file is rotated
+    pub log_max_size: u64,
+    /// Number of rotated log files to keep
+    pub log_keep: u32,
+    /// Seconds between update checks of `fleet --daemon`
+    pub update_interval: u64,
+    /// Seconds players get before a server is restarted onto an update anyway
+    pub update_grace_period: u64,
+    /// Message sent to the players over rcon, `{minutes}` is replaced with the time left
+    pub update_warning: String,
+    /// Seconds between getstatus queries checking that the server responds, 0 disables the check
+    pub health_check_interval: u64,
+    /// Missed responses in a row after which the server is killed and restarted
+    pub health_check_failures: u32,
+    /// Seconds after the start before the first check, loading the map can take a while
+    pub health_check_delay: u64,
+    /// Address serving Prometheus metrics at `/metrics`, e.g. `127.0.0.1:9184`. Disabled if empty
+    pub metrics_address: String,
+}
+
+impl Default for SupervisorConfig {
+    fn default() -> Self {
+        Self {
+            max_restarts: 5,
+            restart_window: 600,
+            backoff_initial: 2,
+            backoff_max: 300,
+            log_dir: String::from("logs"),
+            log_max_size: 10 * 1024 * 1024,
+            log_keep: 5,
+            update_interval: 3600,
+            update_grace_period: 600,
+            update_warning: String::from("Server restarting for an update in {minutes} minutes"),
+            health_check_interval: 30,
+            health_check_failures: 3,
+            health_check_delay: 120,
+            metrics_address: String::default(),
         }
     }
 }
 
+#[derive(serde::Deserialize, serde::Serialize, PartialEq, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookFormat {
+    #[default]
+    Json,
+    Discord,
+}
+
+/// URL receiving a POST for update, launch and crash events
+#[derive(serde::Deserialize, serde::Serialize, PartialEq, Debug, Clone, Default)]
+pub struct Webhook {
+    pub url: String,
+    #[serde(default)]
+    pub format: WebhookFormat,
+    /// Names of the events sent to this webhook, all events if empty
+    #[serde(default)]
+    pub events: Vec<String>,
+}
+
+/// Logs of the game's output, one per launch
+#[derive(serde::Deserialize, serde::Serialize, PartialEq, Debug, Clone)]
+#[serde(default)]
+pub struct GameLogConfig {
+    /// Number of session logs kept per client, 0 disables the logs
+    pub sessions: u32,
+    /// Size in bytes at which a session log is rotated
+    pub max_size: u64,
+}
+
+impl Default for GameLogConfig {
+    fn default() -> Self {
+        Self {
+            sessions: 10,
+            max_size: 10 * 1024 * 1024,
+        }
+    }
+}
+
+/// Commands run before and after updates and game launches, through `sh -c` or `cmd /C`
+#[derive(serde::Deserialize, serde::Serialize, PartialEq, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Hooks {
+    /// Runs before an update, the update is aborted if it fails
+    pub pre_update: String,
+    pub post_update: String,
+    /// Runs before the game starts, the launch is aborted if it fails
+    pub pre_launch: String,
+    pub post_exit: String,
+}
+
+/// Dedicated server started and supervised by `fleet`
+#[derive(serde::Deserialize, serde::Serialize, PartialEq, Debug, Clone, Default)]
+pub struct ServerInstance {
+    pub name: String,
+    pub client: String,
+    /// Install path of the client, the launcher's install path if empty
+    #[serde(default)]
+    pub path: String,
+    pub port: u16,
+    /// Config executed on startup, e.g. `server.cfg`
+    #[serde(default)]
+    pub server_cfg: String,
+    #[serde(default)]
+    pub args: String,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, PartialEq, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum InstanceStatus {
+    #[default]
+    Starting,
+    Running,
+    Restarting,
+    /// Held down while its install is updated
+    Updating,
+    Stopped,
+    Crashed,
+}
+
+/// State of a supervised instance, written by the supervisor for `fleet status`
+#[derive(serde::Deserialize, serde::Serialize, PartialEq, Debug, Clone, Default)]
+pub struct InstanceState {
+    /// Launcher process supervising the instance
+    pub pid: u32,
+    pub server_pid: Option<u32>,
+    pub status: InstanceStatus,
+    pub restarts: u32,
+    /// Unix timestamp the supervisor started at
+    pub started: u64,
+}
+
+/// Program, arguments and environment the game is started with
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameCommand {
+    pub program: PathBuf,
+    pub args: Vec<String>,
+    pub env: Vec<(String, String)>,
+    pub dir: PathBuf,
+}
+
+impl GameCommand {
+    pub fn command(&self) -> std::process::Command {
+        let mut command = std::process::Command::new(&self.program);
+        command
+            .args(&self.args)
+            .envs(self.env.iter().cloned())
+            .current_dir(&self.dir);
+        command
+    }
+}
+
 pub struct PrintPrefix {
     pub text: ColoredString,
     pub padding: usize,
@@ -92,6 +363,18 @@ pub struct Cache {
     pub hashes: HashMap<String, String>,
     #[serde(default)]
     pub stored_data: Option<StoredGameData>,
+    #[serde(default)]
+    pub servers: Vec<SavedServer>,
+    #[serde(default)]
+    pub staged_launch: Option<StagedLaunch>,
+}
+
+/// Client and args for the next launch through Steam, picked up by `--steam-wrap`
+#[derive(serde::Deserialize, serde::Serialize, Default, Debug, Clone, PartialEq)]
+pub struct StagedLaunch {
+    pub client: String,
+    pub args: Vec<String>,
+    pub timestamp: u64,
 }
 
 #[derive(serde::Deserialize, serde::Serialize, Default, Debug, Clone, PartialEq)]
@@ -99,4 +382,21 @@ pub struct StoredGameData {
     pub game_path: String,
     #[serde(default)]
     pub clients: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    pub app_ids: HashMap<String, u32>,
+}
+
+/// Server joined through `--connect`, a link or the server browser
+#[derive(serde::Deserialize, serde::Serialize, Default, Debug, Clone, PartialEq)]
+pub struct SavedServer {
+    pub address: String,
+    #[serde(default)]
+    pub hostname: String,
+    #[serde(default)]
+    pub favorite: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Unix timestamp of the last connect, 0 if never joined
+    #[serde(default)]
+    pub last_played: u64,
 }
@@ -0,0 +1,562 @@
+use crate::logs::{self, RotatingLog};
+use crate::structs::{GameCommand, InstanceState, InstanceStatus, SupervisorConfig};
+use crate::{hooks, metrics, servers, webhooks};
+use std::{
+    collections::VecDeque,
+    fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    process::{Child, ExitStatus, Stdio},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// How often the child and pending signals are checked
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Time a server gets to quit after a stop request before it is killed
+pub const STOP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Time a server gets to answer a health check
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RestartPolicy {
+    pub max_restarts: u32,
+    pub window: Duration,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl RestartPolicy {
+    pub fn from_config(cfg: &SupervisorConfig) -> Self {
+        Self {
+            max_restarts: cfg.max_restarts,
+            window: Duration::from_secs(cfg.restart_window),
+            initial_backoff: Duration::from_secs(cfg.backoff_initial),
+            max_backoff: Duration::from_secs(cfg.backoff_max),
+        }
+    }
+}
+
+/// Keeps track of recent crashes to detect crash loops
+pub struct CrashTracker {
+    policy: RestartPolicy,
+    crashes: VecDeque<Instant>,
+}
+
+impl CrashTracker {
+    pub fn new(policy: RestartPolicy) -> Self {
+        Self {
+            policy,
+            crashes: VecDeque::new(),
+        }
+    }
+
+    /// Record a crash, returning the delay before the restart or `None` once the server is crash-looping
+    pub fn crashed(&mut self, now: Instant) -> Option<Duration> {
+        while self
+            .crashes
+            .front()
+            .is_some_and(|crash| now.duration_since(*crash) >= self.policy.window)
+        {
+            self.crashes.pop_front();
+        }
+        self.crashes.push_back(now);
+
+        let recent = self.crashes.len() as u32;
+        if recent > self.policy.max_restarts {
+            return None;
+        }
+        let factor = 2u32.saturating_pow(recent - 1);
+        Some(
+            self.policy
+                .initial_backoff
+                .saturating_mul(factor)
+                .min(self.policy.max_backoff),
+        )
+    }
+
+    pub fn recent(&self) -> usize {
+        self.crashes.len()
+    }
+}
+
+/// Polls a server with `getstatus` to notice when it hangs while the process is still alive
+pub struct HealthCheck {
+    address: SocketAddr,
+    interval: Duration,
+    failures: u32,
+    missed: u32,
+    next: Instant,
+    players: Option<(String, u32)>,
+}
+
+impl HealthCheck {
+    /// Check for a server started at `started`, `None` if the check is disabled
+    pub fn from_config(
+        address: SocketAddr,
+        cfg: &SupervisorConfig,
+        started: Instant,
+    ) -> Option<Self> {
+        (cfg.health_check_interval > 0 && cfg.health_check_failures > 0).then(|| Self {
+            address,
+            interval: Duration::from_secs(cfg.health_check_interval),
+            failures: cfg.health_check_failures,
+            missed: 0,
+            next: started + Duration::from_secs(cfg.health_check_delay),
+            players: None,
+        })
+    }
+
+    /// Query the server if a check is due, returning false once it missed too many checks in a row
+    pub fn poll(&mut self, name: &str, now: Instant) -> bool {
+        if now < self.next {
+            return true;
+        }
+        self.next = now + self.interval;
+
+        let Some(info) = servers::query_status(&[self.address], HEALTH_CHECK_TIMEOUT).pop() else {
+            self.missed += 1;
+            warn!(
+                "{name} didn't respond to getstatus on {} ({}/{})",
+                self.address, self.missed, self.failures
+            );
+            return self.missed < self.failures;
+        };
+
+        self.missed = 0;
+        metrics::instance_players(name, info.clients, info.max_clients);
+        // player counts are logged when they change, the checks themselves only in debug logs
+        let players = (info.map.clone(), info.clients);
+        let msg = format!(
+            "{name}: {}/{} players on {}",
+            info.clients, info.max_clients, info.map
+        );
+        if self.players.as_ref() == Some(&players) {
+            debug!("{msg}");
+        } else {
+            info!("{msg}");
+            self.players = Some(players);
+        }
+        true
+    }
+}
+
+/// Port set with `+set net_port <port>` in the game args
+pub fn net_port(args: &[String]) -> Option<u16> {
+    args.windows(2)
+        .find(|pair| pair[0] == "net_port")
+        .and_then(|pair| pair[1].parse().ok())
+}
+
+#[cfg(unix)]
+mod signals {
+    use std::sync::atomic::{AtomicI32, AtomicU32, Ordering};
+
+    pub const TERM: i32 = libc::SIGTERM;
+
+    static COUNT: AtomicU32 = AtomicU32::new(0);
+    static LAST: AtomicI32 = AtomicI32::new(0);
+
+    extern "C" fn on_signal(signal: libc::c_int) {
+        LAST.store(signal, Ordering::SeqCst);
+        COUNT.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn install() {
+        let handler = on_signal as extern "C" fn(libc::c_int);
+        // SAFETY: the handler only stores to atomics, which is async-signal-safe
+        unsafe {
+            libc::signal(libc::SIGTERM, handler as libc::sighandler_t);
+            libc::signal(libc::SIGINT, handler as libc::sighandler_t);
+        }
+    }
+
+    /// Number of signals received so far, every supervised instance tracks which ones it has seen
+    pub fn count() -> u32 {
+        COUNT.load(Ordering::SeqCst)
+    }
+
+    pub fn last() -> i32 {
+        LAST.load(Ordering::SeqCst)
+    }
+
+    /// Signal the child's whole process group, which also reaches the game running under wine
+    pub fn forward(child: &std::process::Child, signal: i32) {
+        // SAFETY: kill has no memory safety requirements
+        unsafe {
+            libc::kill(-(child.id() as libc::pid_t), signal);
+        }
+    }
+
+    pub fn kill(child: &std::process::Child) {
+        forward(child, libc::SIGKILL);
+    }
+}
+
+/// Ctrl+C reaches the whole console on Windows, only stop requests of instances are handled
+#[cfg(windows)]
+mod signals {
+    pub const TERM: i32 = 15;
+
+    pub fn install() {}
+
+    pub fn count() -> u32 {
+        0
+    }
+
+    pub fn last() -> i32 {
+        0
+    }
+
+    /// Console servers can't be asked to quit from outside, so stopping kills them
+    pub fn forward(child: &std::process::Child, _signal: i32) {
+        kill(child);
+    }
+
+    pub fn kill(child: &std::process::Child) {
+        let _ = std::process::Command::new("taskkill")
+            .args(["/F", "/T", "/PID", &child.id().to_string()])
+            .status();
+    }
+}
+
+/// State and request files of a fleet instance, used by `fleet status`, `fleet stop` and the update daemon
+pub struct InstanceFiles {
+    pub state: PathBuf,
+    pub stop: PathBuf,
+    pub restart: PathBuf,
+}
+
+impl InstanceFiles {
+    pub fn new(dir: &Path, name: &str) -> Self {
+        Self {
+            state: dir.join(format!("{name}.json")),
+            stop: dir.join(format!("{name}.stop")),
+            restart: dir.join(format!("{name}.restart")),
+        }
+    }
+
+    pub fn read_state(&self) -> Option<InstanceState> {
+        serde_json::from_str(&fs::read_to_string(&self.state).ok()?).ok()
+    }
+
+    fn write_state(&self, state: &InstanceState) {
+        if let Some(dir) = self.state.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        if let Err(error) = fs::write(&self.state, serde_json::to_string_pretty(state).unwrap()) {
+            error!("Failed to write {}: {error}", self.state.display());
+        }
+    }
+
+    pub fn request_stop(&self) -> std::io::Result<()> {
+        fs::write(&self.stop, "")
+    }
+
+    fn take_stop_request(&self) -> bool {
+        fs::remove_file(&self.stop).is_ok()
+    }
+
+    /// Ask the supervisor to stop the server and keep it down until `release` is called
+    pub fn request_restart(&self) -> std::io::Result<()> {
+        if let Some(dir) = self.restart.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.restart, "")
+    }
+
+    pub fn release(&self) {
+        let _ = fs::remove_file(&self.restart);
+    }
+
+    fn restart_requested(&self) -> bool {
+        self.restart.exists()
+    }
+}
+
+enum Request {
+    Stop(i32),
+    Restart,
+}
+
+/// Notices requests, either a signal to the launcher or the request files of the instance
+struct Requests<'a> {
+    seen: u32,
+    files: Option<&'a InstanceFiles>,
+}
+
+impl Requests<'_> {
+    fn poll(&mut self) -> Option<Request> {
+        let count = signals::count();
+        if count != self.seen {
+            self.seen = count;
+            return Some(Request::Stop(signals::last()));
+        }
+        let files = self.files?;
+        if files.take_stop_request() {
+            return Some(Request::Stop(signals::TERM));
+        }
+        files.restart_requested().then_some(Request::Restart)
+    }
+}
+
+/// Why the child exited
+#[derive(PartialEq)]
+enum Ending {
+    Exited,
+    Stopped,
+    Restart,
+}
+
+/// Wait for the child, forwarding stop and restart requests and killing it once it stops responding
+fn wait(
+    name: &str,
+    child: &mut Child,
+    requests: &mut Requests,
+    mut health: Option<HealthCheck>,
+) -> Result<(ExitStatus, Ending), String> {
+    let mut ending = Ending::Exited;
+    let mut stopping_since: Option<Instant> = None;
+    let mut killed = false;
+    loop {
+        if let Some(status) = child
+            .try_wait()
+            .map_err(|e| format!("Failed to wait for {name}: {e}"))?
+        {
+            return Ok((status, ending));
+        }
+
+        match requests.poll() {
+            Some(Request::Stop(_)) if ending == Ending::Stopped => {
+                crate::println_info!("Killing {name}");
+                signals::kill(child);
+                killed = true;
+            }
+            Some(Request::Stop(signal)) => {
+                crate::println_info!("Stopping {name}, request the stop again to kill it");
+                if stopping_since.is_none() {
+                    signals::forward(child, signal);
+                    stopping_since = Some(Instant::now());
+                }
+                ending = Ending::Stopped;
+            }
+            Some(Request::Restart) if ending == Ending::Exited => {
+                crate::println_info!("Stopping {name} for an update");
+                signals::forward(child, signals::TERM);
+                stopping_since = Some(Instant::now());
+                ending = Ending::Restart;
+            }
+            _ => {}
+        }
+
+        if !killed
+            && stopping_since.is_none()
+            && health
+                .as_mut()
+                .is_some_and(|health| !health.poll(name, Instant::now()))
+        {
+            crate::println_error!("{name} stopped responding, killing it");
+            signals::kill(child);
+            killed = true;
+        }
+
+        if !killed && stopping_since.is_some_and(|since| since.elapsed() >= STOP_TIMEOUT) {
+            crate::println_info!(
+                "{name} didn't stop within {}s, killing it",
+                STOP_TIMEOUT.as_secs()
+            );
+            signals::kill(child);
+            killed = true;
+        }
+
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Sleep for `duration`, returning false if a stop was requested meanwhile
+fn sleep(duration: Duration, requests: &mut Requests) -> bool {
+    let deadline = Instant::now() + duration;
+    while Instant::now() < deadline {
+        if let Some(Request::Stop(_)) = requests.poll() {
+            return false;
+        }
+        std::thread::sleep(POLL_INTERVAL.min(deadline - Instant::now()));
+    }
+    true
+}
+
+/// Keep the server down while a restart is requested, returning false if a stop was requested meanwhile
+fn hold(files: &InstanceFiles, requests: &mut Requests) -> bool {
+    while files.restart_requested() {
+        if let Some(Request::Stop(_)) = requests.poll() {
+            return false;
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+    true
+}
+
+fn report(
+    name: &str,
+    files: Option<&InstanceFiles>,
+    state: &mut InstanceState,
+    status: InstanceStatus,
+    server_pid: Option<u32>,
+) {
+    state.status = status;
+    state.server_pid = server_pid;
+    metrics::instance_state(name, status, state.restarts);
+    if let Some(files) = files {
+        files.write_state(state);
+    }
+}
+
+/// Keep a dedicated server running, restarting it with backoff when it crashes.
+///
+/// Output of the server is written to `log_path`, rotated according to the config.
+/// A clean exit, SIGTERM/SIGINT or a stop request through `files` stops the supervisor.
+/// A restart request through `files` stops the server until the request is released.
+/// With a `health_check` address a server that stops answering `getstatus` is restarted like a crashed one.
+/// The pre_launch and post_exit hooks run with `hook_context` around every start of the server.
+pub fn supervise(
+    name: &str,
+    game: &GameCommand,
+    cfg: &SupervisorConfig,
+    log_path: PathBuf,
+    files: Option<&InstanceFiles>,
+    health_check: Option<SocketAddr>,
+    hook_context: &hooks::Context,
+) -> Result<(), String> {
+    signals::install();
+    let log = RotatingLog::open(&log_path, cfg.log_max_size, cfg.log_keep)
+        .map_err(|e| format!("Failed to open log file {}: {e}", log_path.display()))?;
+    let log = Arc::new(Mutex::new(log));
+    let mut crashes = CrashTracker::new(RestartPolicy::from_config(cfg));
+    let mut requests = Requests {
+        seen: signals::count(),
+        files,
+    };
+    if let Some(files) = files {
+        // stale requests from a previous run would stop or hold the instance right away
+        files.take_stop_request();
+        files.release();
+    }
+
+    let mut state = InstanceState {
+        pid: std::process::id(),
+        started: crate::cache::unix_time(),
+        ..Default::default()
+    };
+
+    crate::println_info!("Supervising {name}, logging to {}", log_path.display());
+    let result = loop {
+        if let Some(files) = files.filter(|files| files.restart_requested()) {
+            report(
+                name,
+                Some(files),
+                &mut state,
+                InstanceStatus::Updating,
+                None,
+            );
+            crate::println_info!("Holding {name} while its install is updated");
+            if !hold(files, &mut requests) {
+                crate::println_info!("Stop requested, not restarting {name}");
+                break Ok(());
+            }
+        }
+
+        if let Err(error) = hooks::run(hooks::Phase::PreLaunch, hook_context) {
+            break Err(error);
+        }
+        let mut command = game.command();
+        command.stdout(Stdio::piped()).stderr(Stdio::piped());
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut command, 0);
+
+        let mut child = match command.spawn() {
+            Ok(child) => child,
+            Err(error) => break Err(format!("Failed to start {name}: {error}")),
+        };
+        info!("{name} started with pid {}", child.id());
+        report(
+            name,
+            files,
+            &mut state,
+            InstanceStatus::Running,
+            Some(child.id()),
+        );
+        // the pipe threads end on their own, waiting for them could hang on processes
+        // like wineserver that inherited the pipes
+        if let Some(stdout) = child.stdout.take() {
+            logs::pipe_lines(stdout, log.clone(), logs::Echo::Off);
+        }
+        if let Some(stderr) = child.stderr.take() {
+            logs::pipe_lines(stderr, log.clone(), logs::Echo::Off);
+        }
+
+        let health =
+            health_check.and_then(|address| HealthCheck::from_config(address, cfg, Instant::now()));
+        let (status, ending) = match wait(name, &mut child, &mut requests, health) {
+            Ok(result) => result,
+            Err(error) => break Err(error),
+        };
+        hooks::run_or_report(
+            hooks::Phase::PostExit,
+            &hooks::Context {
+                exit_code: status.code(),
+                ..hook_context.clone()
+            },
+        );
+
+        match ending {
+            Ending::Stopped => {
+                crate::println_info!("{name} stopped ({status})");
+                break Ok(());
+            }
+            Ending::Restart => continue,
+            Ending::Exited => {}
+        }
+        if status.success() {
+            crate::println_info!("{name} exited cleanly, not restarting");
+            break Ok(());
+        }
+
+        error!("{name} exited with {status}");
+        let delay = crashes.crashed(Instant::now());
+        if delay.is_some() {
+            state.restarts += 1;
+        }
+        webhooks::send(webhooks::Event::ServerCrashed {
+            instance: name.to_string(),
+            status: status.to_string(),
+            restarts: state.restarts,
+            giving_up: delay.is_none(),
+        });
+        let Some(delay) = delay else {
+            break Err(format!(
+                "{name} crashed {} times within {}s, giving up",
+                crashes.recent(),
+                cfg.restart_window
+            ));
+        };
+        report(name, files, &mut state, InstanceStatus::Restarting, None);
+        crate::println_error!(
+            "{name} exited with {status}, restarting in {}s",
+            delay.as_secs()
+        );
+        if !sleep(delay, &mut requests) {
+            crate::println_info!("Stop requested, not restarting {name}");
+            break Ok(());
+        }
+    };
+
+    let status = if result.is_ok() {
+        InstanceStatus::Stopped
+    } else {
+        InstanceStatus::Crashed
+    };
+    report(name, files, &mut state, status, None);
+    result
+}
@@ -0,0 +1,201 @@
+use crate::extend::CutePath;
+use crate::misc;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Time systemd gives the launcher to stop, the supervisor itself kills a server after 30s
+const STOP_TIMEOUT: u64 = 60;
+
+/// Delay before systemd starts the launcher again once the supervisor gave up
+const RESTART_DELAY: u64 = 60;
+
+/// Quote an argument for `ExecStart`, `%` specifiers and `$` variables are escaped
+pub fn exec_quote(arg: &str) -> String {
+    let arg = arg.replace('%', "%%").replace('$', "$$");
+    let needs_quotes = arg.is_empty()
+        || arg
+            .chars()
+            .any(|c| c.is_whitespace() || "\"'\\;".contains(c));
+    if !needs_quotes {
+        return arg;
+    }
+    format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// File name of the unit for a client or `fleet`, with the profile if there is one
+pub fn unit_name(target: &str, profile: Option<&str>) -> String {
+    let profile = profile
+        .map(|profile| {
+            let stem = Path::new(profile)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let stem: String = stem
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() || c == '_' {
+                        c
+                    } else {
+                        '-'
+                    }
+                })
+                .collect();
+            format!("-{stem}")
+        })
+        .unwrap_or_default();
+    format!("alterware-{target}{profile}.service")
+}
+
+/// `/etc/systemd/system` for system units, `$XDG_CONFIG_HOME/systemd/user` otherwise
+pub fn unit_dir(system: bool) -> Option<PathBuf> {
+    if system {
+        Some(PathBuf::from("/etc/systemd/system"))
+    } else {
+        misc::config_home().map(|dir| dir.join("systemd").join("user"))
+    }
+}
+
+/// Content of a unit running `command` from `install_path`.
+///
+/// System units run as `user`, user units as the user whose service manager starts them.
+pub fn render(
+    description: &str,
+    command: &[String],
+    install_path: &Path,
+    env: &[(String, String)],
+    user: Option<&str>,
+) -> String {
+    let exec = command
+        .iter()
+        .map(|arg| exec_quote(arg))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let user = user
+        .map(|user| format!("User={user}\n"))
+        .unwrap_or_default();
+    let env: String = env
+        .iter()
+        .map(|(key, value)| {
+            let assignment = format!("{key}={value}")
+                .replace('%', "%%")
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"");
+            format!("Environment=\"{assignment}\"\n")
+        })
+        .collect();
+    let wanted_by = if user.is_empty() {
+        "default.target"
+    } else {
+        "multi-user.target"
+    };
+
+    format!(
+        "[Unit]\n\
+         Description={description}\n\
+         Wants=network-online.target\n\
+         After=network-online.target\n\
+         \n\
+         [Service]\n\
+         Type=simple\n\
+         {user}\
+         WorkingDirectory={}\n\
+         ExecStart={exec}\n\
+         {env}\
+         StandardInput=null\n\
+         Restart=on-failure\n\
+         RestartSec={RESTART_DELAY}\n\
+         KillMode=mixed\n\
+         TimeoutStopSec={STOP_TIMEOUT}\n\
+         \n\
+         [Install]\n\
+         WantedBy={wanted_by}\n",
+        install_path.to_string_lossy().replace('%', "%%")
+    )
+}
+
+/// Write the unit to `dir`
+pub fn write_unit(dir: &Path, name: &str, content: &str) -> Result<PathBuf, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+    let path = dir.join(name);
+    fs::write(&path, content).map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+    Ok(path)
+}
+
+/// Write a unit running a dedicated server, or the fleet if `target` is `fleet`, from `install_path`.
+///
+/// Servers are supervised and updated on every start, the fleet also while running.
+pub fn install(
+    target: &str,
+    profile: Option<&str>,
+    passthrough: &[String],
+    install_path: &Path,
+    system: bool,
+    print: bool,
+) -> Result<(), String> {
+    let launcher = std::env::current_exe().map_err(|e| e.to_string())?;
+    let install_path = fs::canonicalize(install_path).unwrap_or(install_path.to_path_buf());
+    let mut command = vec![launcher.to_string_lossy().into_owned()];
+    let description = if target == "fleet" {
+        if profile.is_some() {
+            return Err(
+                "--profile can't be used with fleet, instances set their own server_cfg"
+                    .to_string(),
+            );
+        }
+        command.extend(["fleet", "start", "--daemon"].map(String::from));
+        "AlterWare dedicated server fleet".to_string()
+    } else {
+        command.push(target.to_string());
+        format!("AlterWare {target} dedicated server")
+    };
+    command.extend([
+        "--path".to_string(),
+        install_path.to_string_lossy().into_owned(),
+        "--skip-launcher-update".to_string(),
+    ]);
+    if target != "fleet" {
+        command.extend(["--supervise", "--", "-dedicated"].map(String::from));
+        if let Some(profile) = profile {
+            command.extend(["+exec".to_string(), profile.to_string()]);
+        }
+        command.extend(passthrough.iter().cloned());
+    }
+
+    // the service manager's PATH usually lacks user directories with wine or umu-run
+    let mut unit_env = vec![("NO_COLOR".to_string(), "1".to_string())];
+    if let Ok(path) = std::env::var("PATH") {
+        unit_env.push(("PATH".to_string(), path));
+    }
+    let user = system
+        .then(|| {
+            std::env::var("SUDO_USER")
+                .or_else(|_| std::env::var("USER"))
+                .ok()
+        })
+        .flatten();
+    let name = unit_name(target, profile);
+    let unit = render(
+        &description,
+        &command,
+        &install_path,
+        &unit_env,
+        user.as_deref(),
+    );
+
+    if print {
+        print!("{unit}");
+        return Ok(());
+    }
+    let dir = unit_dir(system).ok_or("Unable to determine the systemd unit directory")?;
+    let path = write_unit(&dir, &name, &unit)?;
+    crate::println_info!("Wrote {}", path.cute_path());
+    if system {
+        println!("Enable it with: systemctl daemon-reload && systemctl enable --now {name}");
+    } else {
+        println!("Enable it with: systemctl --user daemon-reload && systemctl --user enable --now {name}");
+        println!("Run loginctl enable-linger to keep it running after logging out.");
+    }
+    Ok(())
+}
@@ -88,6 +88,34 @@ mod misc {
         assert!(!misc::is_program_in_path("nonexistent"));
     }
 
+    #[test]
+    fn download_backoff() {
+        use std::time::Duration;
+        assert_eq!(misc::download_backoff(1), Some(Duration::from_secs(5)));
+        assert_eq!(misc::download_backoff(2), Some(Duration::from_secs(10)));
+        assert_eq!(misc::download_backoff(3), Some(Duration::from_secs(20)));
+        assert_eq!(misc::download_backoff(misc::DOWNLOAD_ATTEMPTS), None);
+    }
+
+    #[test]
+    fn redact_args() {
+        assert_eq!(
+            misc::redact_args("+set rcon_password hunter2 +set sv_hostname \"My Server\""),
+            "+set rcon_password '<redacted>' +set sv_hostname 'My Server'"
+        );
+        assert_eq!(
+            misc::redact_args("+set g_password \"two words\" +map mp_prisonbreak"),
+            "+set g_password '<redacted>' +map mp_prisonbreak"
+        );
+        assert_eq!(misc::redact_args("-headless"), "-headless");
+    }
+
+    #[tokio::test]
+    async fn retry_download_without_terminal() {
+        // without a terminal the user isn't asked, the last attempt gives up right away
+        assert!(!misc::retry_download("Retry?", misc::DOWNLOAD_ATTEMPTS, false).await);
+    }
+
     #[test]
     fn cache_operations() {
         let path = Path::new("tests_tmp");
@@ -105,6 +133,8 @@ mod misc {
                 .into_iter()
                 .collect(),
             stored_data: None,
+            servers: Vec::new(),
+            staged_launch: None,
         };
         cache::save_cache(path, test_cache.clone());
         let loaded_cache = cache::get_cache(path);
@@ -112,6 +142,68 @@ mod misc {
 
         fs::remove_file(&cache_file).unwrap();
     }
+
+    #[test]
+    fn saved_servers() {
+        let path = Path::new("tests_tmp").join("saved_servers");
+        fs::create_dir_all(&path).unwrap();
+
+        cache::record_server(&path, "127.0.0.1:28960", Some("LAN"));
+        cache::record_server(&path, "127.0.0.1:28960", None);
+        cache::set_favorite(&path, "10.0.0.1:27016", true, &["clan".to_string()]);
+        let saved = cache::get_saved_servers(&path);
+        assert_eq!(saved.len(), 2);
+        assert_eq!(saved[0].address, "10.0.0.1:27016");
+        assert!(saved[0].favorite);
+        assert_eq!(saved[0].tags, vec!["clan"]);
+        assert_eq!(saved[1].hostname, "LAN");
+        assert!(saved[1].last_played > 0);
+
+        // unfavoriting an unknown server does nothing, a favorite stays as recent server
+        cache::set_favorite(&path, "10.0.0.2:27016", false, &[]);
+        cache::set_favorite(&path, "10.0.0.1:27016", false, &[]);
+        let saved = cache::get_saved_servers(&path);
+        assert_eq!(saved.len(), 2);
+        assert!(saved.iter().all(|s| !s.favorite));
+
+        // only the most recent non-favorites are kept
+        cache::set_favorite(&path, "10.0.0.1:27016", true, &[]);
+        for port in 1..=30 {
+            cache::record_server(&path, &format!("192.168.0.1:{port}"), None);
+        }
+        let saved = cache::get_saved_servers(&path);
+        assert_eq!(saved.len(), 21);
+        assert!(saved[0].favorite);
+
+        fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn staged_launch() {
+        let path = Path::new("tests_tmp").join("staged_launch");
+        fs::create_dir_all(&path).unwrap();
+
+        cache::stage_launch(
+            &path,
+            "iw6-mod",
+            &["+set".to_string(), "fs_game".to_string()],
+        );
+        let staged = cache::take_staged_launch(&path).unwrap();
+        assert_eq!(staged.client, "iw6-mod");
+        assert_eq!(staged.args, vec!["+set", "fs_game"]);
+        assert_eq!(cache::take_staged_launch(&path), None);
+
+        let mut expired = cache::get_cache(&path);
+        expired.staged_launch = Some(structs::StagedLaunch {
+            client: "iw6-mod".to_string(),
+            args: Vec::new(),
+            timestamp: 0,
+        });
+        cache::save_cache(&path, expired);
+        assert_eq!(cache::take_staged_launch(&path), None);
+
+        fs::remove_dir_all(&path).unwrap();
+    }
 }
 
 mod stored_data {
@@ -129,6 +221,7 @@ mod stored_data {
         let data = StoredGameData {
             game_path: test_path.to_string(),
             clients: test_clients,
+            ..Default::default()
         };
 
         let path = Path::new("tests_tmp");
@@ -143,3 +236,1878 @@ mod stored_data {
         fs::remove_dir_all(path).unwrap();
     }
 }
+
+mod args {
+    use crate::misc;
+
+    #[test]
+    fn split_args() {
+        assert_eq!(
+            misc::split_args(r#"-headless +set sv_hostname "My Server""#),
+            vec!["-headless", "+set", "sv_hostname", "My Server"]
+        );
+        assert_eq!(misc::split_args("  -dedicated  "), vec!["-dedicated"]);
+        assert!(misc::split_args("").is_empty());
+    }
+
+    #[test]
+    fn split_args_unbalanced_quotes() {
+        assert_eq!(
+            misc::split_args(r#"+set sv_hostname "My"#),
+            vec!["+set", "sv_hostname", "\"My"]
+        );
+    }
+
+    #[test]
+    fn split_off() {
+        let mut args: Vec<String> = [
+            "launcher",
+            "iw6-mod",
+            "--steam-wrap",
+            "reaper",
+            "--",
+            "game.exe",
+        ]
+        .iter()
+        .map(|a| a.to_string())
+        .collect();
+        let command = crate::arg_split_off(&mut args, "--steam-wrap");
+        assert_eq!(args, vec!["launcher", "iw6-mod"]);
+        assert_eq!(command, vec!["reaper", "--", "game.exe"]);
+        assert!(crate::arg_split_off(&mut args, "--").is_empty());
+    }
+
+    #[test]
+    fn keeps_launcher_log() {
+        let args = |args: &str| -> Vec<String> {
+            std::iter::once("alterware-launcher")
+                .chain(args.split_whitespace())
+                .map(String::from)
+                .collect()
+        };
+        assert!(crate::keeps_launcher_log(&args("fleet status")));
+        assert!(crate::keeps_launcher_log(&args(
+            "--path /srv/iw6 fleet stop tdm"
+        )));
+        assert!(crate::keeps_launcher_log(&args(
+            "rcon 127.0.0.1:27016 status"
+        )));
+        assert!(crate::keeps_launcher_log(&args("systemd fleet --print")));
+        assert!(!crate::keeps_launcher_log(&args("fleet start --daemon")));
+        assert!(!crate::keeps_launcher_log(&args(
+            "iw6-mod -- +set sv_hostname rcon"
+        )));
+        assert!(!crate::keeps_launcher_log(&args("")));
+    }
+
+    #[test]
+    fn steam_wrapped_exe() {
+        let command: Vec<String> = [
+            "/steam/ubuntu12_32/reaper",
+            "SteamLaunch",
+            "AppId=209160",
+            "--",
+            "/steam/steamapps/common/Proton 9.0/proton",
+            "waitforexitandrun",
+            "/steam/steamapps/common/Call of Duty Ghosts/iw6mp64_ship.exe",
+            "-some_arg",
+        ]
+        .iter()
+        .map(|a| a.to_string())
+        .collect();
+        assert_eq!(crate::steam::wrapped_exe(&command), Some(6));
+        assert_eq!(crate::steam::wrapped_exe(&command[..5]), None);
+    }
+
+    #[test]
+    fn join_args() {
+        let args = vec![
+            "+set".to_string(),
+            "sv_hostname".to_string(),
+            "My Server".to_string(),
+        ];
+        assert_eq!(misc::join_args(&args), "+set sv_hostname 'My Server'");
+        assert_eq!(misc::split_args(&misc::join_args(&args)), args);
+    }
+}
+
+mod connect {
+    use crate::misc;
+
+    #[test]
+    fn parse_connect_uri() {
+        assert_eq!(
+            misc::parse_connect_uri("alterware://connect/127.0.0.1:28960?client=iw6-mod"),
+            Ok(("127.0.0.1:28960".to_string(), Some("iw6-mod".to_string())))
+        );
+        assert_eq!(
+            misc::parse_connect_uri("AlterWare://connect/server.example.com:27016/"),
+            Ok(("server.example.com:27016".to_string(), None))
+        );
+        assert_eq!(
+            misc::parse_connect_uri("alterware://connect/[::1]:28960?foo=bar&client=iw4x"),
+            Ok(("[::1]:28960".to_string(), Some("iw4x".to_string())))
+        );
+    }
+
+    #[test]
+    fn parse_connect_uri_rejects() {
+        assert!(misc::parse_connect_uri("https://connect/127.0.0.1:28960").is_err());
+        assert!(misc::parse_connect_uri("alterware://join/127.0.0.1:28960").is_err());
+        assert!(misc::parse_connect_uri("alterware://connect/127.0.0.1").is_err());
+        assert!(misc::parse_connect_uri("alterware://connect/127.0.0.1:0").is_err());
+        assert!(misc::parse_connect_uri("alterware://connect/1.2.3.4:1;quit").is_err());
+        assert!(misc::parse_connect_uri("alterware://connect/a b:28960").is_err());
+        assert!(
+            misc::parse_connect_uri("alterware://connect/127.0.0.1:28960?client=../../x").is_err()
+        );
+    }
+
+    #[test]
+    fn validate_address() {
+        assert!(misc::validate_address("127.0.0.1:28960").is_ok());
+        assert!(misc::validate_address("127.0.0.1:28960 +quit").is_err());
+        assert!(misc::validate_address(":28960").is_err());
+    }
+}
+
+mod servers {
+    use crate::servers;
+    use std::{
+        net::{SocketAddr, UdpSocket},
+        time::Duration,
+    };
+
+    const OOB: &[u8] = b"\xff\xff\xff\xff";
+
+    /// Stand-in game server answering getinfo and getstatus with the challenge it was sent
+    pub(super) fn game_server(hostname: &'static str, clients: u32) -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address = socket.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let mut buffer = [0u8; 1024];
+            while let Ok((len, from)) = socket.recv_from(&mut buffer) {
+                let request = String::from_utf8_lossy(&buffer[4..len]).into_owned();
+                let (command, challenge) = request.split_once(' ').unwrap();
+                let info = format!(
+                    "\\challenge\\{challenge}\\hostname\\{hostname}\\mapname\\mp_prisonbreak\\gametype\\war\\clients\\{clients}\\bots\\0\\sv_maxclients\\18"
+                );
+                // status responses carry the server cvars
+                let status = format!(
+                    "\\challenge\\{challenge}\\sv_hostname\\{hostname}\\mapname\\mp_prisonbreak\\g_gametype\\war\\sv_maxclients\\18"
+                );
+                let response = match command {
+                    "getinfo" => format!("infoResponse\n{info}"),
+                    "getstatus" => format!("statusResponse\n{status}\n100 42 \"^1Player\"\n"),
+                    _ => continue,
+                };
+                socket
+                    .send_to(&[OOB, response.as_bytes()].concat(), from)
+                    .unwrap();
+            }
+        });
+        address
+    }
+
+    /// Stand-in master server listing `servers` over two packets
+    fn master_server(servers: Vec<SocketAddr>) -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address = socket.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let mut buffer = [0u8; 1024];
+            let (len, from) = socket.recv_from(&mut buffer).unwrap();
+            assert_eq!(
+                &buffer[..len],
+                b"\xff\xff\xff\xffgetservers IW6 1 full empty"
+            );
+
+            for (i, server) in servers.iter().enumerate() {
+                let SocketAddr::V4(server) = server else {
+                    unreachable!()
+                };
+                let mut packet = [OOB, b"getserversResponse\n\\"].concat();
+                packet.extend_from_slice(&server.ip().octets());
+                packet.extend_from_slice(&server.port().to_be_bytes());
+                if i == servers.len() - 1 {
+                    packet.extend_from_slice(b"\\EOT\0\0\0");
+                }
+                socket.send_to(&packet, from).unwrap();
+            }
+        });
+        address
+    }
+
+    #[test]
+    fn parse_info_string() {
+        let info = servers::parse_info_string("\\hostname\\^1My ^7Server\\mapname\\mp_sovereign");
+        assert_eq!(info["hostname"], "^1My ^7Server");
+        assert_eq!(info["mapname"], "mp_sovereign");
+        assert_eq!(servers::strip_colors(&info["hostname"]), "My Server");
+    }
+
+    #[test]
+    fn parse_servers_response() {
+        let mut packet = [OOB, b"getserversResponse\n\\"].concat();
+        packet.extend_from_slice(&[127, 0, 0, 1, 0x6f, 0x60]);
+        packet.extend_from_slice(b"\\EOT\0\0\0");
+        let (list, done) = servers::parse_servers_response(&packet).unwrap();
+        assert_eq!(list, vec!["127.0.0.1:28512".parse().unwrap()]);
+        assert!(done);
+
+        assert!(servers::parse_servers_response(b"\xff\xff\xff\xffinfoResponse\n").is_none());
+    }
+
+    #[test]
+    fn parse_servers_response_without_newline() {
+        // dpmaster puts the records right after the name, this port contains a newline byte
+        let mut packet = [OOB, b"getserversResponse\\"].concat();
+        packet.extend_from_slice(&[10, 0, 0, 2, 0x0a, 0x0a]);
+        packet.extend_from_slice(b"\\EOT\0\0\0");
+        let (list, done) = servers::parse_servers_response(&packet).unwrap();
+        assert_eq!(list, vec!["10.0.0.2:2570".parse().unwrap()]);
+        assert!(done);
+
+        assert_eq!(
+            servers::oob_payload(b"\xff\xff\xff\xffprint\nline\n", "print"),
+            Some(&b"line\n"[..])
+        );
+    }
+
+    #[test]
+    fn query() {
+        let first = game_server("^2First", 3);
+        let second = game_server("Second", 0);
+        let master = master_server(vec![first, second]);
+
+        let list =
+            servers::query_master(&master.to_string(), "IW6", 1, Duration::from_secs(5)).unwrap();
+        assert_eq!(list.len(), 2);
+
+        let infos = servers::query_servers(&list, Duration::from_secs(5));
+        assert_eq!(infos.len(), 2);
+        let first = infos.iter().find(|s| s.address == first).unwrap();
+        assert_eq!(first.hostname, "First");
+        assert_eq!(first.map, "mp_prisonbreak");
+        assert_eq!(first.clients, 3);
+        assert_eq!(first.max_clients, 18);
+        assert_eq!(first.players[0].name, "Player");
+        assert_eq!(first.players[0].ping, 42);
+
+        let filter = servers::Filter {
+            not_empty: true,
+            ..Default::default()
+        };
+        let filtered = servers::filter_sort(infos.clone(), &filter, servers::SortKey::Players);
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].hostname, "First");
+
+        let sorted = servers::filter_sort(infos, &Default::default(), servers::SortKey::Name);
+        assert_eq!(sorted[0].hostname, "First");
+        assert_eq!(sorted[1].hostname, "Second");
+    }
+
+    #[test]
+    fn query_status() {
+        let server = game_server("^3LAN", 1);
+        let infos = servers::query_status(&[server], Duration::from_secs(5));
+        assert_eq!(infos.len(), 1);
+        assert_eq!(infos[0].hostname, "LAN");
+        assert_eq!(infos[0].gametype, "war");
+        assert_eq!(infos[0].clients, 1);
+        assert_eq!(infos[0].players[0].name, "Player");
+    }
+
+    #[test]
+    fn format_age() {
+        assert_eq!(servers::format_age(5), "just now");
+        assert_eq!(servers::format_age(300), "5m ago");
+        assert_eq!(servers::format_age(7200), "2h ago");
+        assert_eq!(servers::format_age(259200), "3d ago");
+    }
+
+    #[test]
+    fn sort_key() {
+        assert_eq!("ping".parse(), Ok(servers::SortKey::Ping));
+        assert!("size".parse::<servers::SortKey>().is_err());
+    }
+}
+
+mod rcon {
+    use crate::rcon::{self, Rcon};
+    use std::{net::UdpSocket, time::Duration};
+
+    /// Stand-in dedicated server answering `status` in two packets
+    fn rcon_server() -> String {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address = socket.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let mut buffer = [0u8; 1024];
+            while let Ok((len, from)) = socket.recv_from(&mut buffer) {
+                let request = String::from_utf8_lossy(&buffer[..len]).into_owned();
+                let responses: &[&str] = if request.ends_with("rcon hunter2 status") {
+                    &[
+                        "print\nmap: mp_prisonbreak\n",
+                        "print\nnum score ping name\n",
+                    ]
+                } else if request.ends_with("rcon hunter2 quiet") {
+                    &[]
+                } else {
+                    &["print\nInvalid password.\n"]
+                };
+                for response in responses {
+                    socket
+                        .send_to(&[b"\xff\xff\xff\xff", response.as_bytes()].concat(), from)
+                        .unwrap();
+                }
+            }
+        });
+        address.to_string()
+    }
+
+    #[test]
+    fn parse_print_response() {
+        assert_eq!(
+            rcon::parse_print_response(b"\xff\xff\xff\xffprint\nhello\n"),
+            Some("hello\n".to_string())
+        );
+        assert_eq!(rcon::parse_print_response(b"print\nhello"), None);
+    }
+
+    #[test]
+    fn send() {
+        let address = rcon_server();
+
+        let rcon = Rcon::new(&address, "hunter2", Duration::from_secs(5)).unwrap();
+        assert_eq!(
+            rcon.send("status").unwrap(),
+            "map: mp_prisonbreak\nnum score ping name\n"
+        );
+
+        let rcon = Rcon::new(&address, "wrong", Duration::from_secs(5)).unwrap();
+        assert_eq!(rcon.send("status").unwrap(), "Invalid password.\n");
+
+        let rcon = Rcon::new(&address, "hunter2", Duration::from_millis(200)).unwrap();
+        assert!(rcon.send("quiet").is_err());
+
+        assert!(Rcon::new(&address, "", Duration::from_secs(5)).is_err());
+    }
+}
+
+mod supervisor {
+    use crate::hooks::Context;
+    use crate::logs::RotatingLog;
+    use crate::supervisor::{CrashTracker, RestartPolicy};
+    use std::{
+        fs,
+        path::Path,
+        time::{Duration, Instant},
+    };
+
+    #[test]
+    fn crash_backoff() {
+        let mut crashes = CrashTracker::new(RestartPolicy {
+            max_restarts: 3,
+            window: Duration::from_secs(60),
+            initial_backoff: Duration::from_secs(2),
+            max_backoff: Duration::from_secs(5),
+        });
+        let start = Instant::now();
+        assert_eq!(crashes.crashed(start), Some(Duration::from_secs(2)));
+        assert_eq!(
+            crashes.crashed(start + Duration::from_secs(10)),
+            Some(Duration::from_secs(4))
+        );
+        assert_eq!(
+            crashes.crashed(start + Duration::from_secs(20)),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(crashes.crashed(start + Duration::from_secs(30)), None);
+
+        // crashes older than the window are forgotten
+        assert_eq!(
+            crashes.crashed(start + Duration::from_secs(85)),
+            Some(Duration::from_secs(4))
+        );
+    }
+
+    #[test]
+    fn rotating_log() {
+        let dir = Path::new("tests_tmp").join("rotating_log");
+        let path = dir.join("server.log");
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut log = RotatingLog::open(&path, 10, 2).unwrap();
+        for line in ["first", "second", "third", "fourth"] {
+            log.write_line(line.as_bytes()).unwrap();
+        }
+        assert_eq!(fs::read_to_string(&path).unwrap(), "fourth\n");
+        assert_eq!(
+            fs::read_to_string(dir.join("server.log.1")).unwrap(),
+            "third\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("server.log.2")).unwrap(),
+            "second\n"
+        );
+        assert!(!dir.join("server.log.3").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[serial_test::serial(hooks)]
+    fn supervise() {
+        use crate::structs::{GameCommand, SupervisorConfig};
+        use crate::supervisor;
+
+        let dir = Path::new("tests_tmp").join("supervise");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let cfg = SupervisorConfig {
+            max_restarts: 1,
+            backoff_initial: 0,
+            ..Default::default()
+        };
+        let game = |script: &str| GameCommand {
+            program: "sh".into(),
+            args: vec!["-c".to_string(), script.to_string()],
+            env: Vec::new(),
+            dir: dir.clone(),
+        };
+
+        // restarted once, then the crash loop limit is hit
+        let log_path = dir.join("crash.log");
+        let result = supervisor::supervise(
+            "crash",
+            &game("echo crashed; exit 3"),
+            &cfg,
+            log_path.clone(),
+            None,
+            None,
+            &Context::default(),
+        );
+        assert!(result.unwrap_err().contains("2 times"));
+        std::thread::sleep(Duration::from_millis(200));
+        assert_eq!(fs::read_to_string(&log_path).unwrap(), "crashed\ncrashed\n");
+
+        // a clean exit stops the supervisor
+        let log_path = dir.join("clean.log");
+        assert!(supervisor::supervise(
+            "clean",
+            &game("echo done >&2"),
+            &cfg,
+            log_path.clone(),
+            None,
+            None,
+            &Context::default(),
+        )
+        .is_ok());
+        std::thread::sleep(Duration::from_millis(200));
+        assert_eq!(fs::read_to_string(&log_path).unwrap(), "done\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[serial_test::serial(hooks)]
+    fn hooks_on_every_start() {
+        use crate::hooks;
+        use crate::structs::{GameCommand, Hooks, SupervisorConfig};
+        use crate::supervisor;
+
+        let dir = Path::new("tests_tmp").join("supervise_hooks");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let cfg = SupervisorConfig {
+            max_restarts: 1,
+            backoff_initial: 0,
+            ..Default::default()
+        };
+        let game = GameCommand {
+            program: "sh".into(),
+            args: vec!["-c".to_string(), "exit 3".to_string()],
+            env: Vec::new(),
+            dir: dir.clone(),
+        };
+
+        hooks::configure(Hooks {
+            pre_launch: "echo \"$ALTERWARE_HOOK $ALTERWARE_CLIENT\" >> hooks.txt".to_string(),
+            post_exit: "echo \"$ALTERWARE_HOOK $ALTERWARE_EXIT_CODE\" >> hooks.txt".to_string(),
+            ..Default::default()
+        });
+        let context = Context::new("iw6", "iw6-mod", &dir);
+        let result = supervisor::supervise(
+            "hooks",
+            &game,
+            &cfg,
+            dir.join("hooks.log"),
+            None,
+            None,
+            &context,
+        );
+        hooks::configure(Hooks::default());
+
+        assert!(result.is_err());
+        assert_eq!(
+            fs::read_to_string(dir.join("hooks.txt")).unwrap(),
+            "pre_launch iw6-mod\npost_exit 3\npre_launch iw6-mod\npost_exit 3\n"
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn health_check() {
+        use crate::structs::SupervisorConfig;
+        use crate::supervisor::HealthCheck;
+        use std::net::UdpSocket;
+
+        let cfg = SupervisorConfig {
+            health_check_interval: 5,
+            health_check_failures: 2,
+            health_check_delay: 0,
+            ..Default::default()
+        };
+        let start = Instant::now();
+
+        let server = super::servers::game_server("Healthy", 1);
+        let mut health = HealthCheck::from_config(server, &cfg, start).unwrap();
+        assert!(health.poll("healthy", start));
+
+        // bound but never answering, like a hung server
+        let hung = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut health = HealthCheck::from_config(hung.local_addr().unwrap(), &cfg, start).unwrap();
+        assert!(health.poll("hung", start));
+        // the next check isn't due yet
+        assert!(health.poll("hung", start + Duration::from_secs(1)));
+        assert!(!health.poll("hung", start + Duration::from_secs(5)));
+
+        let disabled = SupervisorConfig {
+            health_check_interval: 0,
+            ..Default::default()
+        };
+        assert!(HealthCheck::from_config(server, &disabled, start).is_none());
+    }
+
+    #[test]
+    fn net_port() {
+        use crate::supervisor;
+
+        let args: Vec<String> = ["-dedicated", "+set", "net_port", "28961", "+map_rotate"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(supervisor::net_port(&args), Some(28961));
+        assert_eq!(supervisor::net_port(&args[..2]), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[serial_test::serial(hooks)]
+    fn restart_hung_server() {
+        use crate::structs::{GameCommand, SupervisorConfig};
+        use crate::supervisor;
+        use std::net::UdpSocket;
+
+        let dir = Path::new("tests_tmp").join("restart_hung_server");
+        let _ = fs::remove_dir_all(&dir);
+        let hung = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let cfg = SupervisorConfig {
+            max_restarts: 0,
+            health_check_interval: 1,
+            health_check_failures: 1,
+            health_check_delay: 0,
+            ..Default::default()
+        };
+        let game = GameCommand {
+            program: "sh".into(),
+            args: vec!["-c".to_string(), "sleep 30".to_string()],
+            env: Vec::new(),
+            dir: std::env::current_dir().unwrap(),
+        };
+
+        let start = Instant::now();
+        let result = supervisor::supervise(
+            "hung",
+            &game,
+            &cfg,
+            dir.join("hung.log"),
+            None,
+            Some(hung.local_addr().unwrap()),
+            &Context::default(),
+        );
+        // killed like a crash, which hits the crash limit right away
+        assert!(result.unwrap_err().contains("1 times"));
+        assert!(start.elapsed() < Duration::from_secs(10));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
+
+mod logs {
+    use crate::logs;
+    use crate::structs::GameLogConfig;
+    use std::{fs, path::Path};
+
+    #[test]
+    fn sessions() {
+        let dir = Path::new("tests_tmp").join("game_logs");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for time in ["1700000001", "1700000002", "1700000003"] {
+            fs::write(dir.join(format!("iw6-mod-{time}.log")), "").unwrap();
+        }
+        fs::write(dir.join("iw6-mod-1700000001.log.1"), "").unwrap();
+        fs::write(dir.join("iw5-mod-1700000001.log"), "").unwrap();
+        fs::write(dir.join("iw6-mod-notes.log"), "").unwrap();
+
+        assert_eq!(
+            logs::sessions(&dir, "iw6-mod"),
+            vec![
+                dir.join("iw6-mod-1700000001.log"),
+                dir.join("iw6-mod-1700000002.log"),
+                dir.join("iw6-mod-1700000003.log"),
+            ]
+        );
+
+        let cfg = GameLogConfig {
+            sessions: 2,
+            max_size: 1024,
+        };
+        let (path, mut log) = logs::open_session(&dir, "iw6-mod", &cfg).unwrap();
+        log.write_line(b"Loading fastfile code_post_gfx_mp")
+            .unwrap();
+        assert_eq!(
+            logs::sessions(&dir, "iw6-mod"),
+            vec![dir.join("iw6-mod-1700000003.log"), path.clone()]
+        );
+        assert!(!dir.join("iw6-mod-1700000001.log.1").exists());
+        // other clients and unrelated files are left alone
+        assert!(dir.join("iw5-mod-1700000001.log").exists());
+        assert!(dir.join("iw6-mod-notes.log").exists());
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "Loading fastfile code_post_gfx_mp\n"
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
+
+mod crash {
+    use crate::crash;
+    use crate::structs::{CdnFile, Config, Hooks, ServerInstance, Webhook};
+    use std::{
+        fs,
+        io::Read,
+        path::Path,
+        time::{Duration, SystemTime},
+    };
+
+    #[test]
+    fn redact_log_short_values() {
+        let cfg = Config {
+            rcon_password: "abc".to_string(),
+            hooks: Hooks {
+                pre_launch: "true".to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        // short values are only redacted where they are set, not wherever they appear
+        assert_eq!(
+            crash::redact_log("[INFO] abcd is true\n+set rcon_password abc\n", &cfg),
+            "[INFO] abcd is true\n+set rcon_password <redacted>\n"
+        );
+    }
+
+    #[test]
+    fn redacted_config() {
+        let cfg = Config {
+            rcon_password: "hunter2".to_string(),
+            args: "+set sv_password secret".to_string(),
+            env: [
+                ("STEAM_API_TOKEN".to_string(), "abc".to_string()),
+                ("DXVK_HUD".to_string(), "fps".to_string()),
+            ]
+            .into(),
+            webhooks: vec![Webhook {
+                url: "https://discord.com/api/webhooks/1/token".to_string(),
+                ..Default::default()
+            }],
+            instances: vec![ServerInstance {
+                name: "tdm".to_string(),
+                client: "iw6-mod".to_string(),
+                port: 27016,
+                args: "+set rcon_password hunter2".to_string(),
+                ..Default::default()
+            }],
+            hooks: Hooks {
+                post_exit: "curl -d @report https://paste.example/upload?key=abc123".to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let config = crash::redacted_config(&cfg);
+        let text = config.to_string();
+        assert!(!text.contains("hunter2"));
+        assert!(!text.contains("secret"));
+        assert!(!text.contains("token"));
+        assert!(!text.contains("abc123"));
+        assert_eq!(config["hooks"]["post_exit"], "<redacted>");
+        assert_eq!(config["hooks"]["pre_launch"], "");
+        assert_eq!(config["env"]["STEAM_API_TOKEN"], "<redacted>");
+        assert_eq!(config["env"]["DXVK_HUD"], "fps");
+        assert_eq!(config["instances"][0]["port"], 27016);
+    }
+
+    #[test]
+    fn write_report() {
+        let dir = Path::new("tests_tmp").join("crash_report");
+        let _ = fs::remove_dir_all(&dir);
+        let install = dir.join("install");
+        fs::create_dir_all(install.join("minidumps")).unwrap();
+        fs::write(install.join("iw6-mod.exe"), "client").unwrap();
+        fs::write(install.join("minidumps").join("iw6-mod.dmp"), "dump").unwrap();
+        fs::write(install.join("notes.txt"), "not a dump").unwrap();
+        let game_log = dir.join("iw6-mod-1700000000.log");
+        fs::write(&game_log, "Error: couldn't load fastfile\n").unwrap();
+
+        let started = SystemTime::now() - Duration::from_secs(60);
+        assert_eq!(
+            crash::crash_dumps(&install, started),
+            vec![install.join("minidumps").join("iw6-mod.dmp")]
+        );
+        assert!(
+            crash::crash_dumps(&install, SystemTime::now() + Duration::from_secs(60)).is_empty()
+        );
+
+        let manifest = vec![
+            CdnFile {
+                name: "iw6/iw6-mod.exe".to_string(),
+                size: 6,
+                blake3: blake3::hash(b"client").to_hex().to_string(),
+            },
+            CdnFile {
+                name: "iw6/data/mod.ff".to_string(),
+                size: 3,
+                blake3: "aaaa".to_string(),
+            },
+        ];
+        assert_eq!(
+            crash::hash_report(&install, "iw6", &manifest),
+            "ok         iw6-mod.exe\nmissing    data/mod.ff\n"
+        );
+
+        let launcher_log = dir.join("alterware-launcher.log");
+        fs::write(
+            &launcher_log,
+            "[INFO] Launch command: wine iw6-mod.exe +set rcon_password hunter2 +set g_password \"two words\"\n\
+             [INFO] Launch env: WINEPREFIX=/prefixes/iw6\n\
+             [INFO] Launch env: STEAM_API_TOKEN=abc123\n",
+        )
+        .unwrap();
+        let cfg = Config {
+            rcon_password: "hunter2".to_string(),
+            ..Default::default()
+        };
+
+        let crash = crash::Crash {
+            client: "iw6-mod",
+            install_path: &install,
+            exit_status: "exit status: 3".to_string(),
+            started,
+            game_log: Some(&game_log),
+            launcher_log: Some(&launcher_log),
+        };
+        let path = crash::write_report(&dir, &crash, &cfg, Ok(manifest)).unwrap();
+        let mut archive = zip::ZipArchive::new(fs::File::open(&path).unwrap()).unwrap();
+        let mut names: Vec<&str> = archive.file_names().collect();
+        names.sort();
+        assert!(names.contains(&"summary.txt"));
+        assert!(names.contains(&"game.log"));
+        assert!(names.contains(&"alterware-launcher.json"));
+        assert!(names.contains(&"awcache.json"));
+        assert!(names.contains(&"hashes.txt"));
+        assert!(names.contains(&"dumps/iw6-mod.dmp"));
+
+        let mut summary = String::new();
+        archive
+            .by_name("summary.txt")
+            .unwrap()
+            .read_to_string(&mut summary)
+            .unwrap();
+        assert!(summary.contains("Client: iw6-mod"));
+        assert!(summary.contains("Exit: exit status: 3"));
+
+        let mut log = String::new();
+        archive
+            .by_name("launcher.log")
+            .unwrap()
+            .read_to_string(&mut log)
+            .unwrap();
+        assert!(log.contains("+set rcon_password <redacted>"));
+        assert!(log.contains("WINEPREFIX=/prefixes/iw6"));
+        assert!(log.contains("+set g_password <redacted>\n"));
+        for secret in ["hunter2", "two", "words", "abc123"] {
+            assert!(!log.contains(secret), "{secret} in launcher.log");
+        }
+        for i in 0..archive.len() {
+            let mut content = Vec::new();
+            archive
+                .by_index(i)
+                .unwrap()
+                .read_to_end(&mut content)
+                .unwrap();
+            assert!(!String::from_utf8_lossy(&content).contains("hunter2"));
+        }
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
+
+mod metrics {
+    use crate::metrics;
+    use crate::structs::InstanceStatus;
+    use std::io::{Read, Write};
+
+    #[test]
+    fn render() {
+        metrics::instance_state("metrics-tdm", InstanceStatus::Running, 2);
+        metrics::instance_players("metrics-tdm", 5, 18);
+        metrics::instance_state("metrics-dom", InstanceStatus::Crashed, 6);
+        metrics::add_downloaded(1024);
+
+        let output = metrics::render();
+        assert!(output.contains("# TYPE alterware_instance_up gauge\n"));
+        assert!(output.contains("\nalterware_instance_up{instance=\"metrics-tdm\"} 1\n"));
+        assert!(output.contains("\nalterware_instance_up{instance=\"metrics-dom\"} 0\n"));
+        assert!(
+            output.contains("\nalterware_instance_restarts_total{instance=\"metrics-tdm\"} 2\n")
+        );
+        assert!(output.contains("\nalterware_instance_players{instance=\"metrics-tdm\"} 5\n"));
+        assert!(output.contains("\nalterware_instance_max_players{instance=\"metrics-tdm\"} 18\n"));
+        // the players of a server that isn't running are unknown
+        assert!(!output.contains("alterware_instance_players{instance=\"metrics-dom\"}"));
+        assert!(
+            output.contains("\nalterware_instance_uptime_seconds{instance=\"metrics-dom\"} 0\n")
+        );
+        assert!(output.contains("\nalterware_downloaded_bytes_total "));
+    }
+
+    fn get(address: std::net::SocketAddr, path: &str) -> String {
+        let mut stream = std::net::TcpStream::connect(address).unwrap();
+        write!(stream, "GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn serve() {
+        metrics::update_finished(true);
+        let address = metrics::serve("127.0.0.1:0").unwrap();
+
+        let response = get(address, "/metrics");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("\r\n\r\n# HELP "));
+        assert!(response.contains("\nalterware_last_update_success 1\n"));
+
+        assert!(get(address, "/").starts_with("HTTP/1.1 404 Not Found\r\n"));
+    }
+}
+
+mod fleet {
+    use crate::fleet;
+    use crate::hooks::Context;
+    use crate::structs::ServerInstance;
+
+    fn instance(name: &str, port: u16) -> ServerInstance {
+        ServerInstance {
+            name: name.to_string(),
+            client: "iw6-mod".to_string(),
+            port,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn instance_args() {
+        let mut server = instance("tdm", 27016);
+        assert_eq!(
+            fleet::instance_args(&server),
+            vec!["-dedicated", "+set", "net_port", "27016"]
+        );
+
+        server.server_cfg = "server_tdm.cfg".to_string();
+        server.args = "+set sv_hostname 'My Server' +map_rotate".to_string();
+        assert_eq!(
+            fleet::instance_args(&server),
+            vec![
+                "-dedicated",
+                "+set",
+                "net_port",
+                "27016",
+                "+exec",
+                "server_tdm.cfg",
+                "+set",
+                "sv_hostname",
+                "My Server",
+                "+map_rotate"
+            ]
+        );
+    }
+
+    #[test]
+    fn validate() {
+        assert!(fleet::validate(&[instance("tdm", 27016), instance("dom", 27017)]).is_ok());
+        assert!(fleet::validate(&[instance("tdm", 27016), instance("tdm", 27017)]).is_err());
+        assert!(fleet::validate(&[instance("tdm", 27016), instance("dom", 27016)]).is_err());
+        assert!(fleet::validate(&[instance("../tdm", 27016)]).is_err());
+        assert!(fleet::validate(&[instance("tdm", 0)]).is_err());
+    }
+
+    #[test]
+    fn select() {
+        let instances = [instance("tdm", 27016), instance("dom", 27017)];
+        assert_eq!(fleet::select(&instances, None).unwrap().len(), 2);
+        assert_eq!(
+            fleet::select(&instances, Some("dom")).unwrap()[0].port,
+            27017
+        );
+        assert!(fleet::select(&instances, Some("ffa")).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[serial_test::serial(hooks)]
+    fn stop_instance() {
+        use crate::structs::{GameCommand, InstanceStatus, SupervisorConfig};
+        use crate::supervisor::{self, InstanceFiles};
+        use std::{fs, path::Path, time::Duration};
+
+        let dir = Path::new("tests_tmp").join("stop_instance");
+        let _ = fs::remove_dir_all(&dir);
+        let files = InstanceFiles::new(&dir, "tdm");
+        let game = GameCommand {
+            program: "sh".into(),
+            args: vec!["-c".to_string(), "sleep 30".to_string()],
+            env: Vec::new(),
+            dir: std::env::current_dir().unwrap(),
+        };
+
+        std::thread::scope(|scope| {
+            let handle = scope.spawn(|| {
+                supervisor::supervise(
+                    "tdm",
+                    &game,
+                    &SupervisorConfig::default(),
+                    dir.join("tdm.log"),
+                    Some(&files),
+                    None,
+                    &Context::default(),
+                )
+            });
+
+            for _ in 0..50 {
+                if fleet::running_state(&files).is_some() {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(100));
+            }
+            let state = fleet::running_state(&files).unwrap();
+            assert_eq!(state.status, InstanceStatus::Running);
+            assert!(state.server_pid.is_some());
+
+            files.request_stop().unwrap();
+            assert!(handle.join().unwrap().is_ok());
+        });
+
+        assert_eq!(files.read_state().unwrap().status, InstanceStatus::Stopped);
+        assert!(fleet::running_state(&files).is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[serial_test::serial(hooks)]
+    fn hold_for_update() {
+        use crate::structs::{GameCommand, InstanceState, InstanceStatus, SupervisorConfig};
+        use crate::supervisor::{self, InstanceFiles};
+        use std::{fs, path::Path, time::Duration};
+
+        let dir = Path::new("tests_tmp").join("hold_for_update");
+        let _ = fs::remove_dir_all(&dir);
+        let files = InstanceFiles::new(&dir, "tdm");
+        let game = GameCommand {
+            program: "sh".into(),
+            args: vec!["-c".to_string(), "sleep 30".to_string()],
+            env: Vec::new(),
+            dir: std::env::current_dir().unwrap(),
+        };
+        let wait_for = |check: &dyn Fn(&InstanceState) -> bool| {
+            for _ in 0..50 {
+                if let Some(state) = fleet::running_state(&files).filter(|state| check(state)) {
+                    return state;
+                }
+                std::thread::sleep(Duration::from_millis(100));
+            }
+            panic!("instance didn't reach the expected state");
+        };
+
+        std::thread::scope(|scope| {
+            let handle = scope.spawn(|| {
+                supervisor::supervise(
+                    "tdm",
+                    &game,
+                    &SupervisorConfig::default(),
+                    dir.join("tdm.log"),
+                    Some(&files),
+                    None,
+                    &Context::default(),
+                )
+            });
+
+            let first = wait_for(&|state| state.status == InstanceStatus::Running);
+
+            // the server stays down until the request is released
+            files.request_restart().unwrap();
+            let held = wait_for(&|state| state.status == InstanceStatus::Updating);
+            assert!(held.server_pid.is_none());
+            std::thread::sleep(Duration::from_millis(300));
+            assert_eq!(
+                fleet::running_state(&files).unwrap().status,
+                InstanceStatus::Updating
+            );
+
+            files.release();
+            let second = wait_for(&|state| state.status == InstanceStatus::Running);
+            assert_ne!(first.server_pid, second.server_pid);
+            // an update restart isn't counted as a crash
+            assert_eq!(second.restarts, 0);
+
+            files.request_stop().unwrap();
+            assert!(handle.join().unwrap().is_ok());
+        });
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn occupied() {
+        use crate::updater;
+
+        let full = super::servers::game_server("Full", 1);
+        let tdm = instance("tdm", full.port());
+        // nothing listens on port 9 on localhost
+        let down = instance("dom", 9);
+        let occupied = updater::occupied(&[&tdm, &down]);
+        assert_eq!(occupied.len(), 1);
+        assert_eq!(occupied[0].0.name, "tdm");
+        assert_eq!(occupied[0].1, 1);
+    }
+
+    #[test]
+    fn warning_message() {
+        use crate::updater;
+        use std::time::Duration;
+
+        let template = "Restarting in {minutes} minutes";
+        assert_eq!(
+            updater::warning_message(template, Duration::from_secs(600)),
+            "Restarting in 10 minutes"
+        );
+        assert_eq!(
+            updater::warning_message(template, Duration::from_secs(61)),
+            "Restarting in 2 minutes"
+        );
+        assert_eq!(
+            updater::warning_message(template, Duration::from_secs(5)),
+            "Restarting in 1 minutes"
+        );
+    }
+
+    #[test]
+    fn apply_staged() {
+        use crate::{cache, updater};
+        use std::{fs, path::Path};
+
+        let dir = Path::new("tests_tmp").join("apply_staged");
+        let _ = fs::remove_dir_all(&dir);
+        let staging = updater::staging_dir(&dir);
+        fs::create_dir_all(staging.join("data")).unwrap();
+        fs::write(dir.join("iw6-mod.exe"), "old").unwrap();
+        fs::write(staging.join("iw6-mod.exe"), "new").unwrap();
+        fs::write(staging.join("data").join("mod.ff"), "mod").unwrap();
+
+        let staged = [
+            updater::StagedFile {
+                name: "iw6-mod.exe".to_string(),
+                hash: "aaaa".to_string(),
+                size: 3,
+            },
+            updater::StagedFile {
+                name: "data/mod.ff".to_string(),
+                hash: "bbbb".to_string(),
+                size: 3,
+            },
+        ];
+        updater::apply(&dir, &staged).unwrap();
+
+        assert_eq!(fs::read_to_string(dir.join("iw6-mod.exe")).unwrap(), "new");
+        assert_eq!(
+            fs::read_to_string(dir.join("data").join("mod.ff")).unwrap(),
+            "mod"
+        );
+        assert!(!staging.exists());
+        let hashes = cache::get_cache(&dir).hashes;
+        assert_eq!(hashes["iw6-mod.exe"], "aaaa");
+        assert_eq!(hashes["data/mod.ff"], "bbbb");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rename_and_delete_files() {
+        use crate::structs::Game;
+        use std::{fs, path::Path};
+
+        let dir = Path::new("tests_tmp").join("rename_delete");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("old_dir")).unwrap();
+        fs::write(dir.join("old.dll"), "dll").unwrap();
+        fs::write(dir.join("unused.dll"), "unused").unwrap();
+
+        let game = Game {
+            engine: "iw6",
+            client: vec!["iw6-mod"],
+            references: Vec::new(),
+            app_id: 209160,
+            bonus: Vec::new(),
+            delete: vec!["unused.dll", "old_dir"],
+            required: Vec::new(),
+            rename: vec![("old.dll", "new.dll")],
+        };
+        game.rename_files(&dir);
+        game.delete_files(&dir);
+
+        assert_eq!(fs::read_to_string(dir.join("new.dll")).unwrap(), "dll");
+        assert!(!dir.join("old.dll").exists());
+        assert!(!dir.join("unused.dll").exists());
+        assert!(!dir.join("old_dir").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
+
+#[cfg(unix)]
+mod runner {
+    use crate::runner::{self, Runner};
+    use crate::structs::Config;
+    use std::path::Path;
+
+    fn strings(words: &[&str]) -> Vec<String> {
+        words.iter().map(|w| w.to_string()).collect()
+    }
+
+    #[test]
+    fn expand_default_template() {
+        let command = runner::expand_template(
+            runner::DEFAULT_LAUNCH_COMMAND,
+            &strings(&["umu-run"]),
+            Path::new("/games/iw6/iw6-mod.exe"),
+            &strings(&["+set", "sv_hostname", "My Server"]),
+        );
+        assert_eq!(
+            command,
+            strings(&[
+                "umu-run",
+                "/games/iw6/iw6-mod.exe",
+                "+set",
+                "sv_hostname",
+                "My Server"
+            ])
+        );
+    }
+
+    #[test]
+    fn expand_native_template() {
+        let command = runner::expand_template(
+            "gamemoderun {runner} {exe} {args}",
+            &[],
+            Path::new("/games/s1/s1-mod.exe"),
+            &[],
+        );
+        assert_eq!(command, strings(&["gamemoderun", "/games/s1/s1-mod.exe"]));
+    }
+
+    #[test]
+    fn expand_embedded_placeholder() {
+        let command = runner::expand_template(
+            "gamescope -f -- {runner} --exe={exe}",
+            &strings(&["wine"]),
+            Path::new("/games/my game/iw6-mod.exe"),
+            &[],
+        );
+        assert_eq!(
+            command,
+            strings(&[
+                "gamescope",
+                "-f",
+                "--",
+                "wine",
+                "--exe=/games/my game/iw6-mod.exe"
+            ])
+        );
+    }
+
+    #[test]
+    fn runner_from_config() {
+        let mut cfg = Config {
+            runner: "wine".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(Runner::from_config(&cfg), Ok(Runner::Wine));
+
+        cfg.runner = "custom".to_string();
+        assert!(Runner::from_config(&cfg).is_err());
+
+        cfg.runner_command = "/opt/wine-ge/bin/wine --verbose".to_string();
+        assert_eq!(
+            Runner::from_config(&cfg),
+            Ok(Runner::Custom(strings(&[
+                "/opt/wine-ge/bin/wine",
+                "--verbose"
+            ])))
+        );
+
+        cfg.runner = "dosbox".to_string();
+        assert!(Runner::from_config(&cfg).is_err());
+    }
+
+    #[test]
+    fn wine_prefix() {
+        let mut cfg = Config {
+            engine: "iw6".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(runner::wine_prefix(&cfg), None);
+
+        cfg.manage_prefix = true;
+        let prefix = runner::wine_prefix(&cfg).unwrap();
+        assert!(prefix.ends_with("alterware-launcher/prefixes/iw6"));
+
+        cfg.wine_prefix = "/srv/wine/iw6".to_string();
+        assert_eq!(
+            runner::wine_prefix(&cfg),
+            Some(Path::new("/srv/wine/iw6").to_path_buf())
+        );
+    }
+
+    #[test]
+    fn launch_env() {
+        let mut cfg = Config::default();
+        cfg.env.insert("WINEDEBUG".to_string(), "-all".to_string());
+        cfg.env
+            .insert("WINEPREFIX".to_string(), "/custom/prefix".to_string());
+
+        cfg.wine_prefix = "/managed/prefix".to_string();
+
+        let env = runner::launch_env(
+            &cfg,
+            &Runner::Wine,
+            Path::new("/games/iw6/iw6-mod.exe"),
+            None,
+        );
+        assert_eq!(
+            env,
+            vec![
+                ("WINEDEBUG".to_string(), "-all".to_string()),
+                ("WINEPREFIX".to_string(), "/custom/prefix".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn proton_launch_env() {
+        let cfg = Config {
+            wine_prefix: "/managed/prefix".to_string(),
+            ..Default::default()
+        };
+        let env = runner::launch_env(
+            &cfg,
+            &Runner::Proton(Path::new("/proton").to_path_buf()),
+            Path::new("/library/steamapps/common/Call of Duty Ghosts/iw6-mod.exe"),
+            Some(209160),
+        );
+        assert!(env.contains(&(
+            "STEAM_COMPAT_DATA_PATH".to_string(),
+            "/library/steamapps/compatdata/209160".to_string()
+        )));
+        assert!(env.contains(&("SteamAppId".to_string(), "209160".to_string())));
+        assert!(!env.iter().any(|(k, _)| k == "WINEPREFIX"));
+    }
+
+    #[test]
+    fn native_launch_env() {
+        let cfg = Config {
+            engine: "iw6".to_string(),
+            manage_prefix: true,
+            ..Default::default()
+        };
+        let env = runner::launch_env(
+            &cfg,
+            &Runner::Native,
+            Path::new("/games/iw6/iw6-mod.exe"),
+            None,
+        );
+        assert!(env.is_empty());
+        // nothing is created for native runners, even with a prefix in the environment
+        let prefix = Path::new("tests_tmp").join("native_prefix");
+        let env = [(
+            "WINEPREFIX".to_string(),
+            prefix.to_string_lossy().into_owned(),
+        )];
+        runner::init_prefix(&Runner::Native, &env).unwrap();
+        assert!(!prefix.exists());
+    }
+
+    #[test]
+    fn umu_env() {
+        let mut cfg = Config::default();
+        assert_eq!(
+            runner::umu_env(&cfg, Some(209160)),
+            vec![
+                ("GAMEID".to_string(), "umu-209160".to_string()),
+                ("STORE".to_string(), "steam".to_string()),
+            ]
+        );
+
+        cfg.proton_version = "GE-Proton".to_string();
+        assert_eq!(
+            runner::umu_env(&cfg, None),
+            vec![
+                ("GAMEID".to_string(), "umu-default".to_string()),
+                ("PROTONPATH".to_string(), "GE-Proton".to_string()),
+            ]
+        );
+    }
+}
+
+#[cfg(unix)]
+mod steam {
+    use crate::steam;
+    use std::{cmp::Ordering, path::Path};
+
+    #[test]
+    fn natural_cmp() {
+        assert_eq!(
+            steam::natural_cmp("GE-Proton9-9", "GE-Proton9-20"),
+            Ordering::Less
+        );
+        assert_eq!(
+            steam::natural_cmp("Proton 10.0", "Proton 9.0"),
+            Ordering::Greater
+        );
+        assert_eq!(
+            steam::natural_cmp("Proton 9.0", "Proton 9.0"),
+            Ordering::Equal
+        );
+    }
+
+    #[test]
+    fn compat_data_path() {
+        assert_eq!(
+            steam::compat_data_path(
+                Path::new("/mnt/games/steamapps/common/Call of Duty Ghosts"),
+                209160
+            ),
+            Some(Path::new("/mnt/games/steamapps/compatdata/209160").to_path_buf())
+        );
+    }
+}
+
+mod vdf {
+    use crate::{steam, vdf};
+    use std::path::PathBuf;
+
+    const LIBRARY_FOLDERS: &str = r#"
+"libraryfolders"
+{
+	"0"
+	{
+		"path"		"/home/user/.local/share/Steam"
+		"label"		""
+		"apps"
+		{
+			"228980"		"377743142"
+		}
+	}
+	"1"
+	{
+		"path"		"/mnt/games/SteamLibrary"
+		"apps"
+		{
+			"209160"		"29034823680"
+		}
+	}
+}
+"#;
+
+    const APP_MANIFEST: &str = r#"
+"AppState"
+{
+	"appid"		"209160"
+	"name"		"Call of Duty: Ghosts"
+	// comments are ignored
+	"installdir"		"Call of Duty Ghosts"
+	"InstalledDepots" { "209161" { "manifest" "123" } }
+}
+"#;
+
+    #[test]
+    fn parse() {
+        let root = vdf::parse(APP_MANIFEST).unwrap();
+        assert_eq!(
+            root.path(&["appstate", "InstallDir"])
+                .and_then(|v| v.as_str()),
+            Some("Call of Duty Ghosts")
+        );
+        assert_eq!(
+            root.path(&["AppState", "InstalledDepots", "209161", "manifest"])
+                .and_then(|v| v.as_str()),
+            Some("123")
+        );
+    }
+
+    #[test]
+    fn parse_escapes() {
+        let root = vdf::parse(r#""key" "C:\\Program Files\\Steam" "quote" "a \"b\"""#).unwrap();
+        assert_eq!(
+            root.get("key").and_then(|v| v.as_str()),
+            Some(r"C:\Program Files\Steam")
+        );
+        assert_eq!(root.get("quote").and_then(|v| v.as_str()), Some(r#"a "b""#));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(vdf::parse(r#""AppState" { "appid" "209160""#).is_err());
+        assert!(vdf::parse(r#""unterminated"#).is_err());
+        assert!(vdf::parse(r#""key""#).is_err());
+    }
+
+    #[test]
+    fn library_folders() {
+        assert_eq!(
+            steam::parse_library_folders(LIBRARY_FOLDERS),
+            vec![
+                PathBuf::from("/home/user/.local/share/Steam"),
+                PathBuf::from("/mnt/games/SteamLibrary")
+            ]
+        );
+        assert_eq!(
+            steam::parse_library_folders(
+                r#""LibraryFolders" { "TimeNextStatsReport" "1" "1" "/mnt/old" }"#
+            ),
+            vec![PathBuf::from("/mnt/old")]
+        );
+    }
+
+    #[test]
+    fn binary_roundtrip() {
+        let root = vdf::Vdf::Map(vec![(
+            "shortcuts".to_string(),
+            vdf::Vdf::Map(vec![(
+                "0".to_string(),
+                vdf::Vdf::Map(vec![
+                    ("appid".to_string(), vdf::Vdf::Int(0x80000001)),
+                    (
+                        "AppName".to_string(),
+                        vdf::Vdf::Value("iw6-mod".to_string()),
+                    ),
+                    ("LastPlayTime".to_string(), vdf::Vdf::UInt64(1700000000)),
+                    ("tags".to_string(), vdf::Vdf::Map(Vec::new())),
+                ]),
+            )]),
+        )]);
+        let binary = vdf::to_binary(&root);
+        assert!(binary.starts_with(b"\x00shortcuts\x00\x000\x00\x02appid\x00"));
+        assert!(binary.ends_with(b"\x08\x08\x08\x08"));
+        assert_eq!(vdf::parse_binary(&binary).unwrap(), root);
+        assert!(vdf::parse_binary(&binary[..binary.len() - 3]).is_err());
+    }
+
+    #[test]
+    fn add_shortcuts() {
+        let shortcut = |client: &str| steam::Shortcut {
+            app_name: client.to_string(),
+            exe: PathBuf::from("/usr/bin/alterware-launcher"),
+            start_dir: PathBuf::from("/games/iw6"),
+            launch_options: format!("{client} --path /games/iw6"),
+            icon: None,
+        };
+        let mut root = vdf::Vdf::Map(Vec::new());
+
+        let shortcuts = [shortcut("iw6-mod")];
+        assert_eq!(steam::add_shortcuts(&mut root, &shortcuts).len(), 1);
+        assert_eq!(steam::add_shortcuts(&mut root, &shortcuts).len(), 0);
+
+        let shortcuts = [shortcut("iw6-mod"), shortcut("iw6-mod-sp")];
+        assert_eq!(steam::add_shortcuts(&mut root, &shortcuts).len(), 1);
+
+        let entries = root.get("shortcuts").unwrap().entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].0, "1");
+        assert_eq!(
+            entries[1].1.get("Exe").and_then(vdf::Vdf::as_str),
+            Some("\"/usr/bin/alterware-launcher\"")
+        );
+        assert_eq!(
+            entries[1].1.get("appid"),
+            Some(&vdf::Vdf::Int(shortcuts[1].app_id()))
+        );
+        assert!(shortcuts[1].app_id() & 0x80000000 != 0);
+    }
+
+    #[test]
+    fn compat_tool_vdf() {
+        let tool = vdf::parse(&steam::compat_tool_vdf()).unwrap();
+        let entry = tool
+            .path(&[
+                "compatibilitytools",
+                "compat_tools",
+                steam::COMPAT_TOOL_NAME,
+            ])
+            .unwrap();
+        assert_eq!(
+            entry.get("display_name").and_then(vdf::Vdf::as_str),
+            Some("AlterWare")
+        );
+
+        let manifest = vdf::parse(&steam::tool_manifest_vdf("alterware-launcher")).unwrap();
+        assert_eq!(
+            manifest
+                .path(&["manifest", "commandline"])
+                .and_then(vdf::Vdf::as_str),
+            Some("/alterware-launcher --compat-tool %verb%")
+        );
+    }
+
+    #[test]
+    fn text_roundtrip() {
+        let root = vdf::parse(LIBRARY_FOLDERS).unwrap();
+        assert_eq!(vdf::parse(&vdf::to_text(&root)).unwrap(), root);
+    }
+
+    #[test]
+    fn app_manifest() {
+        assert_eq!(
+            steam::parse_app_manifest(APP_MANIFEST),
+            Some("Call of Duty Ghosts".to_string())
+        );
+    }
+}
+
+#[cfg(unix)]
+mod desktop {
+    use crate::desktop;
+    use std::path::Path;
+
+    #[test]
+    fn exec_quote() {
+        assert_eq!(desktop::exec_quote("iw6-mod"), "iw6-mod");
+        assert_eq!(
+            desktop::exec_quote("/games/Call of Duty Ghosts"),
+            "\"/games/Call of Duty Ghosts\""
+        );
+        assert_eq!(desktop::exec_quote("100%"), "100%%");
+        assert_eq!(desktop::exec_quote("a$b"), r#""a\\$b""#);
+    }
+
+    #[test]
+    fn render() {
+        let entry = desktop::render(
+            "iw6-mod",
+            Path::new("/usr/bin/alterware-launcher"),
+            Path::new("/games/Call of Duty Ghosts"),
+            Path::new("/icons/alterware.png"),
+        );
+        assert!(entry.starts_with("[Desktop Entry]\n"));
+        assert!(entry.contains(
+            "\nExec=/usr/bin/alterware-launcher iw6-mod --path \"/games/Call of Duty Ghosts\"\n"
+        ));
+        assert!(entry.contains("\nPath=/games/Call of Duty Ghosts\n"));
+        assert!(entry.contains("\nIcon=/icons/alterware.png\n"));
+        assert!(entry.contains("\nX-AlterWare-Client=iw6-mod\n"));
+    }
+
+    #[test]
+    fn render_handler() {
+        let entry = desktop::render_handler(
+            Path::new("/usr/bin/alterware-launcher"),
+            Path::new("/games/Call of Duty Ghosts"),
+            Path::new("/icons/alterware.png"),
+        );
+        assert!(entry.contains("\nExec=/usr/bin/alterware-launcher %u\n"));
+        assert!(entry.contains("\nPath=/games/Call of Duty Ghosts\n"));
+        assert!(entry.contains("\nMimeType=x-scheme-handler/alterware;\n"));
+        assert!(!entry.contains("X-AlterWare-Client"));
+    }
+}
+
+#[cfg(unix)]
+mod systemd {
+    use crate::systemd;
+    use std::path::Path;
+
+    #[test]
+    fn exec_quote() {
+        assert_eq!(systemd::exec_quote("--supervise"), "--supervise");
+        assert_eq!(
+            systemd::exec_quote("/games/Call of Duty Ghosts"),
+            "\"/games/Call of Duty Ghosts\""
+        );
+        assert_eq!(systemd::exec_quote("100%"), "100%%");
+        assert_eq!(systemd::exec_quote("$HOME"), "$$HOME");
+        assert_eq!(systemd::exec_quote("say \"hi\""), r#""say \"hi\"""#);
+    }
+
+    #[test]
+    fn unit_name() {
+        assert_eq!(
+            systemd::unit_name("iw6-mod", None),
+            "alterware-iw6-mod.service"
+        );
+        assert_eq!(
+            systemd::unit_name("iw6-mod", Some("server tdm.cfg")),
+            "alterware-iw6-mod-server-tdm.service"
+        );
+        assert_eq!(systemd::unit_name("fleet", None), "alterware-fleet.service");
+    }
+
+    #[test]
+    fn render() {
+        let command: Vec<String> = [
+            "/usr/bin/alterware-launcher",
+            "iw6-mod",
+            "--path",
+            "/games/Call of Duty Ghosts",
+            "--skip-launcher-update",
+            "--supervise",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        let env = [("NO_COLOR".to_string(), "1".to_string())];
+
+        let unit = systemd::render(
+            "AlterWare iw6-mod dedicated server",
+            &command,
+            Path::new("/games/Call of Duty Ghosts"),
+            &env,
+            None,
+        );
+        assert!(unit.starts_with("[Unit]\nDescription=AlterWare iw6-mod dedicated server\n"));
+        assert!(unit.contains(
+            "\nExecStart=/usr/bin/alterware-launcher iw6-mod --path \"/games/Call of Duty Ghosts\" --skip-launcher-update --supervise\n"
+        ));
+        assert!(unit.contains("\nWorkingDirectory=/games/Call of Duty Ghosts\n"));
+        assert!(unit.contains("\nEnvironment=\"NO_COLOR=1\"\n"));
+        assert!(unit.contains("\nRestart=on-failure\n"));
+        assert!(!unit.contains("User="));
+        assert!(unit.ends_with("WantedBy=default.target\n"));
+
+        let unit = systemd::render(
+            "AlterWare iw6-mod dedicated server",
+            &command,
+            Path::new("/games/Call of Duty Ghosts"),
+            &env,
+            Some("gameserver"),
+        );
+        assert!(unit.contains("\nUser=gameserver\n"));
+        assert!(unit.ends_with("WantedBy=multi-user.target\n"));
+    }
+}
+
+mod webhooks {
+    use crate::structs::{Webhook, WebhookFormat};
+    use crate::webhooks::{self, Event};
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        sync::mpsc,
+    };
+
+    fn update_finished(success: bool) -> Event {
+        Event::UpdateFinished {
+            engine: "iw6".to_string(),
+            path: "/games/ghosts".to_string(),
+            success,
+            files_changed: 3,
+            bytes: 2048,
+            error: (!success).then(|| "Failed to GET files.json".to_string()),
+        }
+    }
+
+    #[test]
+    fn json_payload() {
+        let payload = webhooks::json_payload(&update_finished(true));
+        assert_eq!(payload["event"], "update_finished");
+        assert_eq!(payload["engine"], "iw6");
+        assert_eq!(payload["files_changed"], 3);
+        assert_eq!(payload["bytes"], 2048);
+        assert!(payload.get("error").is_none());
+        assert!(payload["timestamp"].as_u64().unwrap() > 0);
+
+        let payload = webhooks::json_payload(&Event::GameExited {
+            client: "iw6-mod".to_string(),
+            success: false,
+            exit_code: Some(1),
+            status: "exit status: 1".to_string(),
+        });
+        assert_eq!(payload["event"], "game_exited");
+        assert_eq!(payload["exit_code"], 1);
+    }
+
+    #[test]
+    fn discord_payload() {
+        let payload = webhooks::discord_payload(&update_finished(true));
+        let embed = &payload["embeds"][0];
+        assert_eq!(embed["title"], "Updated iw6");
+        assert!(embed["description"]
+            .as_str()
+            .unwrap()
+            .starts_with("3 files changed, "));
+        assert_eq!(embed["color"], 0x2ecc71);
+
+        let payload = webhooks::discord_payload(&update_finished(false));
+        let embed = &payload["embeds"][0];
+        assert_eq!(embed["title"], "Update of iw6 failed");
+        assert!(embed["description"]
+            .as_str()
+            .unwrap()
+            .starts_with("Failed to GET files.json\n"));
+        assert_eq!(embed["color"], 0xe74c3c);
+    }
+
+    #[test]
+    fn send() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    length = value.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            reader
+                .get_mut()
+                .write_all(b"HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n")
+                .unwrap();
+            sender
+                .send((request_line, String::from_utf8(body).unwrap()))
+                .unwrap();
+        });
+
+        // only events other tests don't send, the webhooks are global
+        webhooks::configure(vec![Webhook {
+            url: format!("http://{address}/hook"),
+            format: WebhookFormat::Json,
+            events: vec!["update_started".to_string()],
+        }]);
+        webhooks::send(Event::UpdateStarted {
+            engine: "iw6".to_string(),
+            path: "/games/ghosts".to_string(),
+        });
+        webhooks::configure(Vec::new());
+
+        let (request_line, body) = receiver.recv().unwrap();
+        assert!(request_line.starts_with("POST /hook "));
+        let payload: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(payload["event"], "update_started");
+        assert_eq!(payload["path"], "/games/ghosts");
+    }
+}
+
+mod hooks {
+    use crate::hooks::{self, Context, Phase};
+    use crate::structs::Hooks;
+
+    #[test]
+    fn env() {
+        let context = Context {
+            changed_files: vec!["iw6-mod.exe".to_string(), "data/mod.ff".to_string()],
+            exit_code: Some(3),
+            ..Context::new("iw6", "iw6-mod", std::path::Path::new("/games/ghosts"))
+        };
+        let env = context.env(Phase::PostExit);
+        let value = |key: &str| {
+            env.iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.as_str())
+                .unwrap()
+        };
+        assert_eq!(value("ALTERWARE_HOOK"), "post_exit");
+        assert_eq!(value("ALTERWARE_ENGINE"), "iw6");
+        assert_eq!(value("ALTERWARE_CLIENT"), "iw6-mod");
+        assert_eq!(value("ALTERWARE_INSTALL_PATH"), "/games/ghosts");
+        assert_eq!(value("ALTERWARE_CHANGED_FILES"), "iw6-mod.exe\ndata/mod.ff");
+        assert_eq!(value("ALTERWARE_EXIT_CODE"), "3");
+
+        let env = Context::default().env(Phase::PreLaunch);
+        assert!(env
+            .iter()
+            .any(|(k, v)| k == "ALTERWARE_EXIT_CODE" && v.is_empty()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[serial_test::serial(hooks)]
+    fn run() {
+        use std::{fs, path::Path};
+
+        let dir = Path::new("tests_tmp").join("hooks");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        hooks::configure(Hooks {
+            pre_launch: "test \"$ALTERWARE_CLIENT\" = iw6-mod".to_string(),
+            post_exit: "echo \"$ALTERWARE_HOOK $ALTERWARE_EXIT_CODE\" > hook.txt".to_string(),
+            ..Default::default()
+        });
+        let mut context = Context::new("iw6", "iw6-mod", &dir);
+        assert!(hooks::run(Phase::PreLaunch, &context).is_ok());
+        // phases without a hook always succeed
+        assert!(hooks::run(Phase::PreUpdate, &context).is_ok());
+
+        context.exit_code = Some(1);
+        hooks::run(Phase::PostExit, &context).unwrap();
+        // hooks run from the install path
+        assert_eq!(
+            fs::read_to_string(dir.join("hook.txt")).unwrap(),
+            "post_exit 1\n"
+        );
+
+        context.client = "iw5-mod".to_string();
+        let error = hooks::run(Phase::PreLaunch, &context).unwrap_err();
+        assert!(error.starts_with("pre_launch hook exited with"));
+
+        hooks::configure(Hooks::default());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
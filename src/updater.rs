@@ -0,0 +1,387 @@
+use crate::extend::*;
+use crate::global::MASTER_URL;
+use crate::structs::{CdnFile, Config, Game, InstanceStatus, ServerInstance};
+use crate::supervisor::{self, InstanceFiles};
+use crate::{cache, fleet, hooks, http_async, metrics, rcon, servers, webhooks};
+use indicatif::ProgressBar;
+use std::{
+    fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+/// How often occupied servers are queried again during the grace period
+const EMPTY_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Shortest interval between update checks, files.json shouldn't be hammered
+const MIN_INTERVAL: u64 = 60;
+
+/// File downloaded to the staging directory, not moved into the install yet
+#[derive(Debug, Clone, PartialEq)]
+pub struct StagedFile {
+    pub name: String,
+    pub hash: String,
+    pub size: u64,
+}
+
+/// Directory new files are downloaded to while the servers keep running
+pub fn staging_dir(dir: &Path) -> PathBuf {
+    dir.join(".alterware-staged")
+}
+
+/// Download the files of `remote_dirs` that differ from the install into its staging directory.
+///
+/// Files staged by an earlier check are reused if their hash still matches.
+pub async fn stage(
+    cdn_info: &Vec<CdnFile>,
+    remote_dirs: &[&str],
+    dir: &Path,
+) -> Result<Vec<StagedFile>, String> {
+    let mut hashes = cache::get_cache(dir).hashes;
+    let pb = ProgressBar::hidden();
+    let client = reqwest::Client::new();
+    let staging = staging_dir(dir);
+
+    let mut staged = Vec::new();
+    for remote_dir in remote_dirs {
+        let remote_dir_pre = format!("{remote_dir}/");
+        for file in crate::outdated_files(cdn_info, remote_dir, dir, &mut hashes, &pb) {
+            let name = file.name.replace(&remote_dir_pre, "");
+            let path = staging.join(&name);
+            let hash = file.blake3.to_lowercase();
+
+            if !path
+                .get_blake3()
+                .is_ok_and(|local| local.to_lowercase() == hash)
+            {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)
+                        .map_err(|e| format!("Failed to create {}: {e}", parent.display()))?;
+                }
+                let url = format!("{}/{}", MASTER_URL.lock().unwrap(), file.name);
+                http_async::download_file_progress(&client, &pb, &url, &path, file.size as u64)
+                    .await?;
+
+                let local = path.get_blake3().map_err(|e| e.to_string())?.to_lowercase();
+                if local != hash {
+                    let _ = fs::remove_file(&path);
+                    return Err(format!(
+                        "Downloaded file hash does not match remote for {name}, remote {hash}, local {local}"
+                    ));
+                }
+            }
+            staged.push(StagedFile {
+                name,
+                hash,
+                size: file.size as u64,
+            });
+        }
+    }
+    Ok(staged)
+}
+
+/// Move staged files into the install and record their hashes, the servers using it must be down
+pub fn apply(dir: &Path, staged: &[StagedFile]) -> Result<(), String> {
+    let staging = staging_dir(dir);
+    let mut cache = cache::get_cache(dir);
+
+    let mut result = Ok(());
+    for file in staged {
+        let target = dir.join(&file.name);
+        if let Some(parent) = target.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if let Err(error) = fs::rename(staging.join(&file.name), &target) {
+            result = Err(format!("Failed to move {} into place: {error}", file.name));
+            break;
+        }
+
+        #[cfg(unix)]
+        if file.name.ends_with(".exe") {
+            let perms = std::os::unix::fs::PermissionsExt::from_mode(0o755);
+            fs::set_permissions(&target, perms).unwrap_or_else(|error| {
+                crate::println_error!("Error setting permissions for {}: {error}", file.name);
+            })
+        }
+        info!("Updated {}", target.cute_path());
+        cache.hashes.insert(file.name.clone(), file.hash.clone());
+    }
+
+    // files moved before a failure are recorded either way
+    cache::save_cache(dir, cache);
+    if result.is_ok() {
+        let _ = fs::remove_dir_all(&staging);
+    }
+    result
+}
+
+/// Instances with players connected and their player count, servers that don't respond count as empty
+pub fn occupied<'a>(instances: &[&'a ServerInstance]) -> Vec<(&'a ServerInstance, u32)> {
+    let addresses: Vec<SocketAddr> = instances
+        .iter()
+        .map(|instance| SocketAddr::from(([127, 0, 0, 1], instance.port)))
+        .collect();
+    let live = servers::query_status(&addresses, Duration::from_secs(1));
+
+    instances
+        .iter()
+        .filter_map(|instance| {
+            live.iter()
+                .find(|info| info.address.port() == instance.port && info.clients > 0)
+                .map(|info| (*instance, info.clients))
+        })
+        .collect()
+}
+
+/// The warning from the config for the time left, rounded up to full minutes
+pub fn warning_message(template: &str, remaining: Duration) -> String {
+    template.replace(
+        "{minutes}",
+        &remaining.as_secs().div_ceil(60).max(1).to_string(),
+    )
+}
+
+fn warn_players(instances: &[(&ServerInstance, u32)], password: &str, message: &str) {
+    for (instance, _) in instances {
+        let address = format!("127.0.0.1:{}", instance.port);
+        let result = rcon::Rcon::new(&address, password, Duration::from_secs(2))
+            .and_then(|rcon| rcon.send(&format!("say \"{}\"", message.replace('"', "'"))));
+        if let Err(error) = result {
+            warn!("Failed to warn the players of {}: {error}", instance.name);
+        }
+    }
+}
+
+/// Wait until the instances are empty or the grace period is over, warning the players over rcon
+async fn wait_for_empty(instances: &[&ServerInstance], cfg: &Config) {
+    let deadline = Instant::now() + Duration::from_secs(cfg.supervisor.update_grace_period);
+    let mut warned = false;
+    loop {
+        let occupied = occupied(instances);
+        if occupied.is_empty() {
+            return;
+        }
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            crate::println_info!("Grace period is over, restarting with players connected");
+            return;
+        }
+
+        if !warned {
+            for (instance, players) in &occupied {
+                crate::println_info!(
+                    "{} has {players} players connected, restarting it when empty or in {}s",
+                    instance.name,
+                    remaining.as_secs()
+                );
+            }
+            let message = warning_message(&cfg.supervisor.update_warning, remaining);
+            warn_players(&occupied, &rcon::password(cfg), &message);
+            warned = true;
+        }
+        tokio::time::sleep(EMPTY_CHECK_INTERVAL.min(remaining)).await;
+    }
+}
+
+/// Stop the instances, move the staged files into place and let the supervisors start them again.
+///
+/// Renames and deletions of the game are applied along with the staged files, like a normal update.
+async fn restart_onto(
+    game: &Game<'_>,
+    dir: &Path,
+    instances: &[&ServerInstance],
+    staged: &[StagedFile],
+    state_dir: &Path,
+) -> Result<(), String> {
+    // servers sharing an install usually run the same client
+    let client = instances
+        .first()
+        .map_or("", |instance| instance.client.as_str());
+    let mut hook_context = hooks::Context::new(game.engine, client, dir);
+    hooks::run(hooks::Phase::PreUpdate, &hook_context)?;
+
+    let files: Vec<InstanceFiles> = instances
+        .iter()
+        .map(|instance| InstanceFiles::new(state_dir, &instance.name))
+        .collect();
+    let result = 'restart: {
+        for (instance, instance_files) in instances.iter().zip(&files) {
+            if let Err(error) = instance_files.request_restart() {
+                break 'restart Err(format!("Failed to restart {}: {error}", instance.name));
+            }
+        }
+
+        // servers that are already down or waiting to be restarted don't hold files open
+        let deadline = Instant::now() + supervisor::STOP_TIMEOUT + Duration::from_secs(10);
+        loop {
+            if files.iter().all(|files| {
+                fleet::running_state(files).is_none_or(|state| {
+                    matches!(
+                        state.status,
+                        InstanceStatus::Updating | InstanceStatus::Restarting
+                    )
+                })
+            }) {
+                game.rename_files(dir);
+                let applied = apply(dir, staged);
+                if applied.is_ok() {
+                    game.delete_files(dir);
+                }
+                break applied;
+            }
+            if Instant::now() >= deadline {
+                break Err("Timed out waiting for the servers to stop".to_string());
+            }
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+    };
+
+    // the post hook runs before the servers start again, also when the update failed
+    if result.is_ok() {
+        hook_context.changed_files = staged.iter().map(|file| file.name.clone()).collect();
+    }
+    hook_context.exit_code = Some(i32::from(result.is_err()));
+    hooks::run_or_report(hooks::Phase::PostUpdate, &hook_context);
+
+    files.iter().for_each(InstanceFiles::release);
+    result
+}
+
+/// Stage the new files of one install and restart its instances onto them.
+///
+/// Returns the number of updated files and their size, `None` if the install is up to date.
+async fn update_install(
+    cdn_info: &Vec<CdnFile>,
+    game: &Game<'_>,
+    path: &Path,
+    instances: &[&ServerInstance],
+    cfg: &Config,
+    state_dir: &Path,
+) -> Result<Option<(usize, u64)>, String> {
+    let mut remote_dirs = vec![game.engine];
+    if cfg.download_bonus_content {
+        remote_dirs.extend(&game.bonus);
+    }
+
+    let staged = stage(cdn_info, &remote_dirs, path).await?;
+    if staged.is_empty() {
+        info!("{} is up to date", path.display());
+        return Ok(None);
+    }
+    crate::println_info!(
+        "Staged {} updated files for {}",
+        staged.len(),
+        path.display()
+    );
+    webhooks::send(webhooks::Event::UpdateStarted {
+        engine: game.engine.to_string(),
+        path: path.to_string_lossy().into_owned(),
+    });
+
+    wait_for_empty(instances, cfg).await;
+    restart_onto(game, path, instances, &staged, state_dir).await?;
+    crate::println_info!("Updated {}, restarting its servers", path.display());
+    Ok(Some((
+        staged.len(),
+        staged.iter().map(|file| file.size).sum(),
+    )))
+}
+
+/// Check files.json once and update every install with new files
+async fn check(
+    games: &[Game<'_>],
+    instances: &[ServerInstance],
+    install_path: &Path,
+    cfg: &Config,
+) -> Result<(), String> {
+    let mut installs: Vec<(PathBuf, &Game, Vec<&ServerInstance>)> = Vec::new();
+    for instance in instances {
+        let path = fleet::instance_path(instance, install_path);
+        let Some(game) = games
+            .iter()
+            .find(|g| g.client.contains(&instance.client.as_str()))
+        else {
+            continue;
+        };
+        match installs.iter_mut().find(|(p, _, _)| *p == path) {
+            Some((_, _, instances)) => instances.push(instance),
+            None => installs.push((path, game, vec![instance])),
+        }
+    }
+
+    let finished = |game: &Game, path: &Path, result: &Result<(usize, u64), String>| {
+        let (files_changed, bytes) = *result.as_ref().unwrap_or(&(0, 0));
+        webhooks::send(webhooks::Event::UpdateFinished {
+            engine: game.engine.to_string(),
+            path: path.to_string_lossy().into_owned(),
+            success: result.is_ok(),
+            files_changed,
+            bytes,
+            error: result.as_ref().err().cloned(),
+        });
+    };
+
+    let cdn_info: Result<Vec<CdnFile>, String> =
+        http_async::get_body_string(&format!("{}/files.json", MASTER_URL.lock().unwrap()))
+            .await
+            .and_then(|body| {
+                serde_json::from_str(&body).map_err(|e| format!("Failed to parse files.json: {e}"))
+            });
+    let cdn_info = match cdn_info {
+        Ok(cdn_info) => cdn_info,
+        Err(error) => {
+            for (path, game, _) in &installs {
+                finished(game, path, &Err(error.clone()));
+            }
+            return Err(error);
+        }
+    };
+
+    let state_dir = fleet::state_dir(install_path);
+    for (path, game, instances) in installs {
+        let result = update_install(&cdn_info, game, &path, &instances, cfg, &state_dir).await;
+        match result {
+            Ok(None) => {}
+            Ok(Some(updated)) => finished(game, &path, &Ok(updated)),
+            Err(error) => {
+                finished(game, &path, &Err(error.clone()));
+                return Err(error);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Check for client updates on a schedule until all supervisors have ended.
+///
+/// New files are staged while the servers keep running and only moved into place
+/// once the servers using the install are empty or the grace period is over.
+pub async fn run(
+    games: &[Game<'_>],
+    instances: &[ServerInstance],
+    install_path: &Path,
+    cfg: &Config,
+    supervisors: &[JoinHandle<()>],
+) {
+    let interval = Duration::from_secs(cfg.supervisor.update_interval.max(MIN_INTERVAL));
+    crate::println_info!("Checking for updates every {}s", interval.as_secs());
+
+    loop {
+        let next = Instant::now() + interval;
+        while Instant::now() < next {
+            if supervisors.iter().all(JoinHandle::is_finished) {
+                return;
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+
+        info!("Checking for updates");
+        let result = check(games, instances, install_path, cfg).await;
+        metrics::update_finished(result.is_ok());
+        if let Err(error) = result {
+            crate::println_error!("Update check failed: {error}");
+        }
+    }
+}
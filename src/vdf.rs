@@ -0,0 +1,286 @@
+use std::{iter::Peekable, str::Chars};
+
+/// Node of a VDF (KeyValues) document such as libraryfolders.vdf or an appmanifest.
+///
+/// Text VDF only has strings and maps, the numeric types appear in binary VDF
+/// files like shortcuts.vdf.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Vdf {
+    Value(String),
+    Int(u32),
+    Float(f32),
+    UInt64(u64),
+    Map(Vec<(String, Vdf)>),
+}
+
+impl Vdf {
+    /// Look up a child by key, keys are case-insensitive like in Steam
+    pub fn get(&self, key: &str) -> Option<&Vdf> {
+        match self {
+            Vdf::Map(entries) => entries
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                .map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Follow a path of keys, e.g. `["AppState", "installdir"]`
+    pub fn path(&self, keys: &[&str]) -> Option<&Vdf> {
+        keys.iter().try_fold(self, |node, key| node.get(key))
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Vdf::Value(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn entries(&self) -> &[(String, Vdf)] {
+        match self {
+            Vdf::Map(entries) => entries,
+            _ => &[],
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Str(String),
+    Open,
+    Close,
+}
+
+struct Tokenizer<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl Tokenizer<'_> {
+    fn next_token(&mut self) -> Result<Option<Token>, String> {
+        loop {
+            match self.chars.peek() {
+                None => return Ok(None),
+                Some(c) if c.is_whitespace() => {
+                    self.chars.next();
+                }
+                Some('/') => {
+                    self.chars.next();
+                    if self.chars.next_if_eq(&'/').is_none() {
+                        return Err("Unexpected '/'".to_string());
+                    }
+                    for c in self.chars.by_ref() {
+                        if c == '\n' {
+                            break;
+                        }
+                    }
+                }
+                // platform conditionals like [$WIN32] are ignored
+                Some('[') => {
+                    for c in self.chars.by_ref() {
+                        if c == ']' {
+                            break;
+                        }
+                    }
+                }
+                Some('{') => {
+                    self.chars.next();
+                    return Ok(Some(Token::Open));
+                }
+                Some('}') => {
+                    self.chars.next();
+                    return Ok(Some(Token::Close));
+                }
+                Some('"') => {
+                    self.chars.next();
+                    return self.quoted().map(|s| Some(Token::Str(s)));
+                }
+                Some(_) => return Ok(Some(Token::Str(self.unquoted()))),
+            }
+        }
+    }
+
+    fn quoted(&mut self) -> Result<String, String> {
+        let mut value = String::new();
+        while let Some(c) = self.chars.next() {
+            match c {
+                '"' => return Ok(value),
+                '\\' => match self.chars.next() {
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some(c) => value.push(c),
+                    None => break,
+                },
+                c => value.push(c),
+            }
+        }
+        Err("Unterminated string".to_string())
+    }
+
+    fn unquoted(&mut self) -> String {
+        let mut value = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if c.is_whitespace() || c == '{' || c == '}' || c == '"' {
+                break;
+            }
+            value.push(c);
+            self.chars.next();
+        }
+        value
+    }
+}
+
+fn parse_map(tokens: &mut Tokenizer, nested: bool) -> Result<Vec<(String, Vdf)>, String> {
+    let mut entries = Vec::new();
+    loop {
+        let key = match tokens.next_token()? {
+            Some(Token::Str(key)) => key,
+            Some(Token::Close) if nested => return Ok(entries),
+            None if !nested => return Ok(entries),
+            Some(token) => return Err(format!("Unexpected token {token:?}")),
+            None => return Err("Unexpected end of input".to_string()),
+        };
+        let value = match tokens.next_token()? {
+            Some(Token::Str(value)) => Vdf::Value(value),
+            Some(Token::Open) => Vdf::Map(parse_map(tokens, true)?),
+            _ => return Err(format!("Missing value for key '{key}'")),
+        };
+        entries.push((key, value));
+    }
+}
+
+/// Parse a text VDF document into its root map
+pub fn parse(input: &str) -> Result<Vdf, String> {
+    let mut tokens = Tokenizer {
+        chars: input.trim_start_matches('\u{feff}').chars().peekable(),
+    };
+    parse_map(&mut tokens, false).map(Vdf::Map)
+}
+
+const BINARY_MAP: u8 = 0x00;
+const BINARY_STRING: u8 = 0x01;
+const BINARY_INT: u8 = 0x02;
+const BINARY_FLOAT: u8 = 0x03;
+const BINARY_UINT64: u8 = 0x07;
+const BINARY_END: u8 = 0x08;
+
+struct BinaryReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl BinaryReader<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8], String> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or("Unexpected end of binary VDF")?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let len = self.data[self.pos..]
+            .iter()
+            .position(|b| *b == 0)
+            .ok_or("Unterminated string in binary VDF")?;
+        let value = String::from_utf8_lossy(self.take(len)?).into_owned();
+        self.pos += 1;
+        Ok(value)
+    }
+
+    fn map(&mut self, nested: bool) -> Result<Vec<(String, Vdf)>, String> {
+        let mut entries = Vec::new();
+        loop {
+            let kind = match self.take(1) {
+                Ok(kind) => kind[0],
+                Err(_) if !nested => return Ok(entries),
+                Err(e) => return Err(e),
+            };
+            if kind == BINARY_END {
+                return Ok(entries);
+            }
+
+            let key = self.string()?;
+            let value = match kind {
+                BINARY_MAP => Vdf::Map(self.map(true)?),
+                BINARY_STRING => Vdf::Value(self.string()?),
+                BINARY_INT => Vdf::Int(u32::from_le_bytes(self.take(4)?.try_into().unwrap())),
+                BINARY_FLOAT => Vdf::Float(f32::from_le_bytes(self.take(4)?.try_into().unwrap())),
+                BINARY_UINT64 => Vdf::UInt64(u64::from_le_bytes(self.take(8)?.try_into().unwrap())),
+                kind => return Err(format!("Unknown binary VDF type {kind:#04x}")),
+            };
+            entries.push((key, value));
+        }
+    }
+}
+
+/// Parse a binary VDF document such as shortcuts.vdf into its root map
+pub fn parse_binary(data: &[u8]) -> Result<Vdf, String> {
+    let mut reader = BinaryReader { data, pos: 0 };
+    reader.map(false).map(Vdf::Map)
+}
+
+fn write_binary_map(entries: &[(String, Vdf)], out: &mut Vec<u8>) {
+    for (key, value) in entries {
+        let kind = match value {
+            Vdf::Map(_) => BINARY_MAP,
+            Vdf::Value(_) => BINARY_STRING,
+            Vdf::Int(_) => BINARY_INT,
+            Vdf::Float(_) => BINARY_FLOAT,
+            Vdf::UInt64(_) => BINARY_UINT64,
+        };
+        out.push(kind);
+        out.extend_from_slice(key.as_bytes());
+        out.push(0);
+
+        match value {
+            Vdf::Map(children) => {
+                write_binary_map(children, out);
+                out.push(BINARY_END);
+            }
+            Vdf::Value(value) => {
+                out.extend_from_slice(value.as_bytes());
+                out.push(0);
+            }
+            Vdf::Int(value) => out.extend_from_slice(&value.to_le_bytes()),
+            Vdf::Float(value) => out.extend_from_slice(&value.to_le_bytes()),
+            Vdf::UInt64(value) => out.extend_from_slice(&value.to_le_bytes()),
+        }
+    }
+}
+
+/// Serialize a root map to binary VDF
+pub fn to_binary(root: &Vdf) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_binary_map(root.entries(), &mut out);
+    out.push(BINARY_END);
+    out
+}
+
+fn write_text_map(entries: &[(String, Vdf)], depth: usize, out: &mut String) {
+    let quote = |value: &str| format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""));
+    let indent = "\t".repeat(depth);
+    for (key, value) in entries {
+        match value {
+            Vdf::Map(children) => {
+                out.push_str(&format!("{indent}{}\n{indent}{{\n", quote(key)));
+                write_text_map(children, depth + 1, out);
+                out.push_str(&format!("{indent}}}\n"));
+            }
+            Vdf::Value(value) => {
+                out.push_str(&format!("{indent}{}\t\t{}\n", quote(key), quote(value)))
+            }
+            Vdf::Int(value) => out.push_str(&format!("{indent}{}\t\t\"{value}\"\n", quote(key))),
+            Vdf::Float(value) => out.push_str(&format!("{indent}{}\t\t\"{value}\"\n", quote(key))),
+            Vdf::UInt64(value) => out.push_str(&format!("{indent}{}\t\t\"{value}\"\n", quote(key))),
+        }
+    }
+}
+
+/// Serialize a root map to text VDF
+pub fn to_text(root: &Vdf) -> String {
+    let mut out = String::new();
+    write_text_map(root.entries(), 0, &mut out);
+    out
+}
@@ -0,0 +1,246 @@
+use crate::structs::{Webhook, WebhookFormat};
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, SyncSender, TrySendError},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// Time a webhook gets to accept an event
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Events waiting to be posted, new events are dropped while the queue is full
+const QUEUE_SIZE: usize = 64;
+
+const COLOR_SUCCESS: u32 = 0x2ecc71;
+const COLOR_FAILURE: u32 = 0xe74c3c;
+
+static WEBHOOKS: Lazy<Mutex<Vec<Webhook>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// Events queued or being posted
+static PENDING: AtomicUsize = AtomicUsize::new(0);
+
+/// Queue of the thread posting events, started with the first event
+static QUEUE: Lazy<SyncSender<(Vec<Webhook>, Event)>> = Lazy::new(|| {
+    let (sender, receiver) = mpsc::sync_channel::<(Vec<Webhook>, Event)>(QUEUE_SIZE);
+    // events come from the async update as well as the supervisor threads,
+    // a thread with its own runtime works for both
+    std::thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build();
+        let client = reqwest::Client::builder().timeout(TIMEOUT).build();
+        for (webhooks, event) in receiver {
+            match (&runtime, &client) {
+                (Ok(runtime), Ok(client)) => runtime.block_on(post_all(client, &webhooks, &event)),
+                (Err(error), _) => error!("Failed to send webhooks: {error}"),
+                (_, Err(error)) => error!("Failed to send webhooks: {error}"),
+            }
+            PENDING.fetch_sub(1, Ordering::SeqCst);
+        }
+    });
+    sender
+});
+
+#[derive(serde::Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    UpdateStarted {
+        engine: String,
+        path: String,
+    },
+    UpdateFinished {
+        engine: String,
+        path: String,
+        success: bool,
+        files_changed: usize,
+        bytes: u64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    GameLaunched {
+        client: String,
+        path: String,
+    },
+    GameExited {
+        client: String,
+        success: bool,
+        exit_code: Option<i32>,
+        status: String,
+    },
+    ServerCrashed {
+        instance: String,
+        status: String,
+        restarts: u32,
+        /// The supervisor hit the crash limit and doesn't restart the server
+        giving_up: bool,
+    },
+}
+
+impl Event {
+    /// Name used in the payload and the `events` filter of a webhook
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::UpdateStarted { .. } => "update_started",
+            Event::UpdateFinished { .. } => "update_finished",
+            Event::GameLaunched { .. } => "game_launched",
+            Event::GameExited { .. } => "game_exited",
+            Event::ServerCrashed { .. } => "server_crashed",
+        }
+    }
+
+    fn is_failure(&self) -> bool {
+        match self {
+            Event::UpdateFinished { success, .. } | Event::GameExited { success, .. } => !success,
+            Event::ServerCrashed { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Title and description for chat messages
+    fn summary(&self) -> (String, String) {
+        match self {
+            Event::UpdateStarted { engine, path } => {
+                (format!("Updating {engine}"), format!("Install: {path}"))
+            }
+            Event::UpdateFinished {
+                engine,
+                path,
+                success: true,
+                files_changed,
+                bytes,
+                ..
+            } => (
+                format!("Updated {engine}"),
+                format!(
+                    "{files_changed} files changed, {} downloaded\nInstall: {path}",
+                    crate::misc::human_readable_bytes(*bytes)
+                ),
+            ),
+            Event::UpdateFinished {
+                engine,
+                path,
+                error,
+                ..
+            } => (
+                format!("Update of {engine} failed"),
+                format!(
+                    "{}\nInstall: {path}",
+                    error.as_deref().unwrap_or("Unknown error")
+                ),
+            ),
+            Event::GameLaunched { client, path } => {
+                (format!("Launched {client}"), format!("Install: {path}"))
+            }
+            Event::GameExited { client, status, .. } => {
+                (format!("{client} exited"), format!("Exited with {status}"))
+            }
+            Event::ServerCrashed {
+                instance,
+                status,
+                restarts,
+                giving_up,
+            } => (
+                format!("{instance} crashed"),
+                if *giving_up {
+                    format!("Exited with {status}, crashed too often and is not restarted anymore")
+                } else {
+                    format!("Exited with {status}, restart {restarts}")
+                },
+            ),
+        }
+    }
+}
+
+/// Webhooks from the config that events are sent to
+pub fn configure(webhooks: Vec<Webhook>) {
+    *WEBHOOKS.lock().unwrap() = webhooks;
+}
+
+/// The event as JSON with a timestamp
+pub fn json_payload(event: &Event) -> Value {
+    let mut payload = serde_json::to_value(event).unwrap();
+    payload["timestamp"] = json!(crate::cache::unix_time());
+    payload
+}
+
+/// The event as a Discord webhook message with an embed
+pub fn discord_payload(event: &Event) -> Value {
+    let (title, description) = event.summary();
+    json!({
+        "username": "AlterWare Launcher",
+        "embeds": [{
+            "title": title,
+            "description": description,
+            "color": if event.is_failure() { COLOR_FAILURE } else { COLOR_SUCCESS },
+        }],
+    })
+}
+
+async fn post(client: &reqwest::Client, webhook: &Webhook, event: &Event) -> Result<(), String> {
+    let payload = match webhook.format {
+        WebhookFormat::Json => json_payload(event),
+        WebhookFormat::Discord => discord_payload(event),
+    };
+    let res = client
+        .post(&webhook.url)
+        .header("User-Agent", crate::global::USER_AGENT.to_string())
+        .header("Content-Type", "application/json")
+        .body(payload.to_string())
+        .send()
+        .await
+        .map_err(|e| format!("Failed to send request: {}", e.without_url()))?;
+    if !res.status().is_success() {
+        return Err(format!("Request failed with status: {}", res.status()));
+    }
+    Ok(())
+}
+
+async fn post_all(client: &reqwest::Client, webhooks: &[Webhook], event: &Event) {
+    // the URLs aren't logged, webhook URLs usually contain a token
+    for (i, webhook) in webhooks.iter().enumerate() {
+        match post(client, webhook, event).await {
+            Ok(()) => debug!("Sent {} to webhook {}", event.name(), i + 1),
+            Err(error) => warn!(
+                "Failed to send {} to webhook {}: {error}",
+                event.name(),
+                i + 1
+            ),
+        }
+    }
+}
+
+/// Queue the event for every webhook that wants it, without waiting for it to be posted
+pub fn send(event: Event) {
+    let webhooks: Vec<Webhook> = WEBHOOKS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|webhook| {
+            webhook.events.is_empty() || webhook.events.iter().any(|name| name == event.name())
+        })
+        .cloned()
+        .collect();
+    if webhooks.is_empty() {
+        return;
+    }
+
+    PENDING.fetch_add(1, Ordering::SeqCst);
+    if let Err(error) = QUEUE.try_send((webhooks, event)) {
+        PENDING.fetch_sub(1, Ordering::SeqCst);
+        let (TrySendError::Full((_, event)) | TrySendError::Disconnected((_, event))) = error;
+        warn!("Webhook queue is full, dropping {}", event.name());
+    }
+}
+
+/// Wait for queued events to be posted before the launcher exits, at most `TIMEOUT`
+pub fn flush() {
+    let deadline = Instant::now() + TIMEOUT;
+    while PENDING.load(Ordering::SeqCst) > 0 && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}